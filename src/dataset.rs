@@ -1,67 +1,825 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::{Context, Result, bail};
 use clap::{Args, ValueEnum};
-use rand::{Rng, rng};
+use fnv::{FnvBuildHasher, FnvHasher};
+use rand::{Rng, SeedableRng, distr::weighted::WeightedIndex, rngs::SmallRng};
+use rand_distr::Normal;
+use serde::Serialize;
 
-use crate::generator::{Generator, SequentialGenerator, UniformGenerator, ZipfianGenerator};
+use crate::generator::{
+    ExponentialGenerator, Generator, HotspotGenerator, LatestGenerator, NormalGenerator,
+    SequentialGenerator, SizeGenerator, UniformGenerator, ZipfianGenerator, check_size_range,
+};
 
-#[derive(Clone, Debug, Args)]
+#[derive(Clone, Debug, Args, Serialize)]
 #[group(skip)]
 pub struct Options {
     #[arg(long, short, default_value_t = 10)]
     pub klen: usize,
+    #[arg(long, value_enum, default_value_t = KeyFormat::Binary)]
+    pub key_format: KeyFormat,
+    /// The prefix of decimal and hashed keys
+    #[arg(long, default_value = "user")]
+    pub key_prefix: String,
+    /// Prepend a prefix component with this many distinct values to every
+    /// key, e.g. a tenant, record `x` falls under prefix `x % num_prefixes`
+    #[arg(long)]
+    pub num_prefixes: Option<u64>,
+    /// The length of the prefix component, text key formats append a `/`
+    /// separator
+    #[arg(long, default_value_t = 4)]
+    pub prefix_len: usize,
+    /// The distribution of prefixes
+    #[arg(long, value_enum, default_value_t = Distribution::Uniform)]
+    pub prefix_distribution: Distribution,
     #[arg(long, short, default_value_t = 100)]
     pub vlen: usize,
+    #[arg(long, value_enum, default_value_t = ValueSizeDistribution::Constant)]
+    pub vlen_distribution: ValueSizeDistribution,
+    /// The minimum value size of uniform, zipfian and normal value sizes
+    #[arg(long, default_value_t = 1)]
+    pub vlen_min: usize,
+    /// The maximum value size of uniform, zipfian and normal value sizes
+    #[arg(long, default_value_t = 1024)]
+    pub vlen_max: usize,
+    /// The standard deviation of normal value sizes around --vlen
+    #[arg(long, default_value_t = 10.0)]
+    pub vlen_stddev: f64,
+    /// A file of `<SIZE> <WEIGHT>` lines for empirical value sizes
+    #[arg(long)]
+    pub vlen_file: Option<PathBuf>,
+    /// The target ratio of compressed to uncompressed value size, values are
+    /// incompressible at 1.0
+    #[arg(long, default_value_t = 1.0)]
+    pub compression_ratio: f64,
+    /// Write values derived from the key and a version with an embedded
+    /// checksum, and check the values read belong to their key and are not
+    /// older than the last write of the record in this run. Tracking versions
+    /// takes 16 bytes per record, records inserted by the run are not tracked
+    #[arg(long, default_value_t = false)]
+    pub verify: bool,
     #[arg(long, short, default_value_t = 1_000_000)]
     pub num_records: usize,
+    /// Use the keys of this file, one per line, instead of generating them,
+    /// overrides --num-records
+    #[arg(long, conflicts_with = "kv_file")]
+    pub keys_file: Option<PathBuf>,
+    /// Use the keys and values of this file instead of generating them,
+    /// overrides --num-records, writes use the value of their key
+    #[arg(long)]
+    pub kv_file: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = KvFileFormat::Csv)]
+    pub kv_file_format: KvFileFormat,
+    /// The encoding of --keys-file lines and --kv-file CSV fields
+    #[arg(long, value_enum, default_value_t = FileEncoding::Raw)]
+    pub file_encoding: FileEncoding,
     #[arg(long, short, value_enum, default_value_t = Distribution::Uniform)]
     pub distribution: Distribution,
+    /// The distribution of read keys [default: --distribution]
+    #[arg(long, value_enum)]
+    pub read_distribution: Option<Distribution>,
+    /// The distribution of write and delete keys [default: --distribution]
+    #[arg(long, value_enum)]
+    pub write_distribution: Option<Distribution>,
+    /// The distribution of scan start keys [default: --distribution]
+    #[arg(long, value_enum)]
+    pub scan_distribution: Option<Distribution>,
+    /// The skew of zipfian and latest distributions
+    #[arg(long, default_value_t = 0.99)]
+    pub zipf_theta: f64,
+    /// The fraction of records that are hot in the hotspot distribution
+    #[arg(long, default_value_t = 0.2)]
+    pub hotspot_fraction: f64,
+    /// The probability of accessing hot records in the hotspot distribution
+    #[arg(long, default_value_t = 0.8)]
+    pub hotspot_probability: f64,
+    /// The percentile of accesses that fall within --exponential-fraction of
+    /// the most recent records in the exponential distribution
+    #[arg(long, default_value_t = 0.95)]
+    pub exponential_percentile: f64,
+    /// The fraction of the most recent records that --exponential-percentile
+    /// of accesses fall within in the exponential distribution
+    #[arg(long, default_value_t = 0.8571)]
+    pub exponential_fraction: f64,
+    /// The mean of the normal distribution as a fraction of the records
+    #[arg(long, default_value_t = 0.5)]
+    pub normal_mean: f64,
+    /// The standard deviation of the normal distribution as a fraction of the
+    /// records
+    #[arg(long, default_value_t = 0.1)]
+    pub normal_stddev: f64,
+}
+
+/// The kind of access a key is generated for.
+#[derive(Copy, Clone, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    Scan,
+}
+
+struct Keys {
+    distribution: Distribution,
+    generator: Box<dyn Generator>,
+}
+
+impl Keys {
+    fn new(distribution: Option<&Distribution>, num_items: u64, options: &Options) -> Result<Self> {
+        let distribution = distribution.unwrap_or(&options.distribution).clone();
+        let generator = distribution
+            .new_generator(num_items, options)
+            .with_context(|| format!("invalid {distribution:?} distribution"))?;
+        Ok(Self {
+            distribution,
+            generator,
+        })
+    }
+
+    /// Generates `0..n`.
+    fn next(&self, n: u64, rng: &mut impl Rng) -> u64 {
+        let x = self.generator.next(rng) % n;
+        match self.distribution {
+            // Skew towards the most recently inserted items
+            Distribution::Latest | Distribution::Exponential => n - 1 - x,
+            _ => x,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum KeyFormat {
+    /// Big-endian bytes of the ordinal, truncated or zero-padded to --klen
+    Binary,
+    /// The prefix followed by the zero-padded decimal ordinal, e.g.
+    /// `user000001234`
+    Decimal,
+    /// The zero-padded hex ordinal
+    Hex,
+    /// The prefix followed by the zero-padded decimal FNV hash of the
+    /// ordinal, like YCSB
+    Hashed,
+}
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum KvFileFormat {
+    /// `<KEY>,<VALUE>` lines
+    Csv,
+    /// Records of a little-endian u32 key length, the key, a little-endian
+    /// u32 value length and the value
+    Binary,
+}
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum FileEncoding {
+    Raw,
+    Hex,
+}
+
+impl FileEncoding {
+    fn decode(&self, field: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Raw => Ok(field.to_vec()),
+            Self::Hex => {
+                let field = field.trim_ascii();
+                if !field.len().is_multiple_of(2) {
+                    bail!("odd length hex '{}'", field.escape_ascii());
+                }
+                // from_str_radix accepts a sign
+                if !field.iter().all(u8::is_ascii_hexdigit) {
+                    bail!("invalid hex '{}'", field.escape_ascii());
+                }
+                field
+                    .chunks(2)
+                    .map(|b| {
+                        let s = std::str::from_utf8(b)?;
+                        Ok(u8::from_str_radix(s, 16)?)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Keys, and values with --kv-file, read from a file.
+struct Records {
+    keys: Vec<Vec<u8>>,
+    values: Vec<Vec<u8>>,
+}
+
+impl Records {
+    fn new(options: &Options) -> Result<Option<Self>> {
+        let records = match (&options.keys_file, &options.kv_file) {
+            (Some(path), _) => Self::read_keys(path, &options.file_encoding)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+            (None, Some(path)) => Self::read_kvs(path, options)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+            (None, None) => return Ok(None),
+        };
+        if records.keys.is_empty() {
+            bail!("no records in the file");
+        }
+        Ok(Some(records))
+    }
+
+    fn read_keys(path: &PathBuf, encoding: &FileEncoding) -> Result<Self> {
+        let mut keys = Vec::new();
+        for line in lines(&fs::read(path)?) {
+            keys.push(encoding.decode(line)?);
+        }
+        Ok(Self {
+            keys,
+            values: Vec::new(),
+        })
+    }
+
+    fn read_kvs(path: &PathBuf, options: &Options) -> Result<Self> {
+        let data = fs::read(path)?;
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        match options.kv_file_format {
+            KvFileFormat::Csv => {
+                for line in lines(&data) {
+                    let Some(i) = line.iter().position(|&b| b == b',') else {
+                        bail!(
+                            "invalid line '{}', expected '<KEY>,<VALUE>'",
+                            line.escape_ascii()
+                        );
+                    };
+                    keys.push(options.file_encoding.decode(&line[..i])?);
+                    values.push(options.file_encoding.decode(&line[i + 1..])?);
+                }
+            }
+            KvFileFormat::Binary => {
+                let mut data = &data[..];
+                while !data.is_empty() {
+                    keys.push(read_field(&mut data)?.to_vec());
+                    values.push(read_field(&mut data)?.to_vec());
+                }
+            }
+        }
+        Ok(Self { keys, values })
+    }
+}
+
+/// Splits non-empty lines, ending with `\n` or `\r\n`.
+fn lines(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    data.split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+}
+
+/// Reads a little-endian u32 length prefixed field.
+fn read_field<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let Some((len, rest)) = data.split_first_chunk::<4>() else {
+        bail!("truncated length");
+    };
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.len() < len {
+        bail!("truncated field of {len} bytes");
+    }
+    let (field, rest) = rest.split_at(len);
+    *data = rest;
+    Ok(field)
+}
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum ValueSizeDistribution {
+    /// Always --vlen
+    Constant,
+    Uniform,
+    /// Favor smaller values
+    Zipfian,
+    Normal,
+    /// Sizes weighted as in --vlen-file
+    Empirical,
+}
+
+enum ValueSize {
+    Size(SizeGenerator),
+    Normal(Normal<f64>, usize, usize),
+    Empirical(Vec<usize>, WeightedIndex<f64>),
+}
+
+impl ValueSize {
+    fn new(options: &Options) -> Result<Self> {
+        let min = options.vlen_min;
+        let max = options.vlen_max;
+        let range = "invalid --vlen-min or --vlen-max";
+        let size = match options.vlen_distribution {
+            ValueSizeDistribution::Constant => Self::Size(SizeGenerator::Constant(options.vlen)),
+            ValueSizeDistribution::Uniform => {
+                Self::Size(SizeGenerator::uniform(min, max).context(range)?)
+            }
+            ValueSizeDistribution::Zipfian => {
+                Self::Size(SizeGenerator::zipfian(min, max).context(range)?)
+            }
+            ValueSizeDistribution::Normal => {
+                check_size_range(min, max).context(range)?;
+                Self::Normal(
+                    Normal::new(options.vlen as f64, options.vlen_stddev)?,
+                    min,
+                    max,
+                )
+            }
+            ValueSizeDistribution::Empirical => {
+                let Some(path) = &options.vlen_file else {
+                    bail!("--vlen-file is required by empirical value sizes");
+                };
+                let (sizes, weights) = read_value_sizes(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                Self::Empirical(sizes, WeightedIndex::new(weights)?)
+            }
+        };
+        Ok(size)
+    }
+
+    fn next(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            Self::Size(size) => size.next(rng),
+            Self::Normal(dist, min, max) => (rng.sample(dist).max(0.0) as usize).clamp(*min, *max),
+            Self::Empirical(sizes, dist) => sizes[rng.sample(dist)],
+        }
+    }
+}
+
+fn read_value_sizes(path: &PathBuf) -> Result<(Vec<usize>, Vec<f64>)> {
+    let mut sizes = Vec::new();
+    let mut weights = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(|c: char| c == ',' || c.is_whitespace());
+        let (Some(size), Some(weight)) = (fields.next(), fields.next()) else {
+            bail!("invalid line '{line}', expected '<SIZE> <WEIGHT>'");
+        };
+        sizes.push(size.parse()?);
+        weights.push(weight.parse()?);
+    }
+    Ok((sizes, weights))
 }
 
 pub struct Dataset {
     options: Options,
-    generator: Box<dyn Generator>,
+    value_size: ValueSize,
+    read: Keys,
+    write: Keys,
+    scan: Keys,
+    prefix: Option<Keys>,
+    records: Option<Records>,
+    // The number of records in the keyspace, grows as inserts are
+    // acknowledged so reads never sample a record still being inserted
+    num_records: AtomicU64,
+    // The ordinal of the next insert
+    next_insert: AtomicU64,
+    // Inserts finished ahead of an earlier one, acknowledged once it is
+    pending_inserts: Mutex<BTreeSet<u64>>,
+    // The version of the next verifiable value
+    version: AtomicU64,
+    // The writes of each record under --verify
+    writes: Vec<RecordWrites>,
+}
+
+/// Tracks the writes of a record to detect stale reads.
+///
+/// A write is clean if no other write of the record overlapped it, then the
+/// database holds its version or a newer one until the next write. Overlapping
+/// writes may reach the database in any order, so the version is unknown
+/// until the next clean write.
+#[derive(Default)]
+struct RecordWrites {
+    // The writes started in the high half and those in flight in the low half
+    state: AtomicU64,
+    // The version of the last clean write + 1, or 0 if unknown
+    acknowledged: AtomicU64,
+}
+
+impl RecordWrites {
+    const STARTED: u64 = 1 << 32;
+}
+
+/// A write generated by `next_record`, to `acknowledge_write` once it
+/// finished.
+pub struct PendingWrite {
+    x: u64,
+    expected: Option<u64>,
+    // The version written and the state of the record after it started
+    tracked: Option<(u64, u64)>,
+}
+
+impl PendingWrite {
+    /// The version a read of the record must return at least before this
+    /// write, if known.
+    pub fn expected(&self) -> Option<u64> {
+        self.expected
+    }
 }
 
 impl Dataset {
-    pub fn new(options: Options) -> Self {
-        let generator = options.distribution.new_generator();
-        Self { options, generator }
+    pub fn new(mut options: Options) -> Result<Self> {
+        for (name, value) in [
+            ("--zipf-theta", options.zipf_theta),
+            ("--exponential-fraction", options.exponential_fraction),
+            ("--normal-mean", options.normal_mean),
+            ("--normal-stddev", options.normal_stddev),
+            ("--vlen-stddev", options.vlen_stddev),
+        ] {
+            if !value.is_finite() {
+                bail!("{name} must be finite, got {value}");
+            }
+        }
+        if !(0.0..).contains(&options.zipf_theta) {
+            bail!("--zipf-theta must not be negative");
+        }
+        if !(0.0..=1.0).contains(&options.hotspot_fraction) {
+            bail!("--hotspot-fraction must be between 0 and 1");
+        }
+        if !(0.0..=1.0).contains(&options.hotspot_probability) {
+            bail!("--hotspot-probability must be between 0 and 1");
+        }
+        if !(options.exponential_percentile > 0.0 && options.exponential_percentile < 1.0) {
+            bail!("--exponential-percentile must be between 0 and 1 exclusive");
+        }
+        if options.exponential_fraction.is_nan() || options.exponential_fraction <= 0.0 {
+            bail!("--exponential-fraction must be positive");
+        }
+        if !(0.0..).contains(&options.normal_stddev) {
+            bail!("--normal-stddev must not be negative");
+        }
+        if options.verify && options.kv_file.is_some() {
+            bail!(
+                "--verify cannot be used with --kv-file, the values of the file are not verifiable"
+            );
+        }
+        if !(options.compression_ratio > 0.0 && options.compression_ratio <= 1.0) {
+            bail!("--compression-ratio must be greater than 0 and at most 1");
+        }
+        let records = Records::new(&options)?;
+        if let Some(records) = &records {
+            if options.num_prefixes.is_some() {
+                bail!("--num-prefixes is incompatible with keys from a file");
+            }
+            options.num_records = records.keys.len();
+        }
+        if options.num_records == 0 {
+            bail!("--num-records must be positive");
+        }
+        let value_size = ValueSize::new(&options)?;
+        let n = options.num_records as u64;
+        let read = Keys::new(options.read_distribution.as_ref(), n, &options)?;
+        let write = Keys::new(options.write_distribution.as_ref(), n, &options)?;
+        let scan = Keys::new(options.scan_distribution.as_ref(), n, &options)?;
+        let prefix = match options.num_prefixes {
+            Some(0) => bail!("--num-prefixes must be positive"),
+            Some(num_prefixes) => Some(Keys::new(
+                Some(&options.prefix_distribution),
+                num_prefixes,
+                &options,
+            )?),
+            None => None,
+        };
+        let writes = if options.verify {
+            (0..n).map(|_| RecordWrites::default()).collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            options,
+            value_size,
+            read,
+            write,
+            scan,
+            prefix,
+            records,
+            num_records: AtomicU64::new(n),
+            next_insert: AtomicU64::new(n),
+            pending_inserts: Mutex::new(BTreeSet::new()),
+            version: AtomicU64::new(0),
+            writes,
+        })
     }
 }
 
 impl Dataset {
-    pub fn next(&self, k: &mut Vec<u8>) {
-        let x = self.generator.next() % self.options.num_records as u64;
-        let b = x.to_be_bytes();
+    /// Generates the key of a record to access, returns its ordinal.
+    pub fn next(&self, access: Access, k: &mut Vec<u8>, rng: &mut impl Rng) -> u64 {
+        let x = self.next_ordinal(access, rng);
+        self.encode(x, k);
+        x
+    }
+
+    fn next_ordinal(&self, access: Access, rng: &mut impl Rng) -> u64 {
+        let keys = match access {
+            Access::Read => &self.read,
+            Access::Write => &self.write,
+            Access::Scan => &self.scan,
+        };
+        let n = self.num_records.load(Ordering::Acquire);
+        let mut x = keys.next(n, rng);
+        // Sequential access walks every record whatever its prefix, otherwise
+        // move to the nearest record under a sampled prefix
+        if let (Some(prefix), Some(num_prefixes)) = (&self.prefix, self.options.num_prefixes)
+            && !matches!(keys.distribution, Distribution::Sequential)
+        {
+            let p = prefix.next(num_prefixes, rng);
+            x = x - x % num_prefixes + p;
+            if x >= n {
+                x = x.saturating_sub(num_prefixes);
+            }
+        }
+        x
+    }
+
+    /// Generates a prefix component to scan, the keys under it are spread
+    /// over the whole keyspace.
+    pub fn next_prefix(&self, k: &mut Vec<u8>, rng: &mut impl Rng) {
+        let (Some(prefix), Some(num_prefixes)) = (&self.prefix, self.options.num_prefixes) else {
+            panic!("prefix scans require --num-prefixes");
+        };
+        let p = prefix.next(num_prefixes, rng);
         k.clear();
-        if let Some(i) = b.len().checked_sub(self.options.klen) {
-            k.extend_from_slice(&b[i..]);
-        } else {
-            k.extend_from_slice(&b);
-            k.resize(self.options.klen, 0);
+        self.encode_prefix(p, k);
+    }
+
+    /// Generates a range from a scan key to the key `n` records later, which
+    /// requires `ordered` keys.
+    pub fn next_range(&self, start: &mut Vec<u8>, end: &mut Vec<u8>, n: u64, rng: &mut impl Rng) {
+        let x = self.next_ordinal(Access::Scan, rng);
+        self.encode(x, start);
+        self.encode(x.saturating_add(n), end);
+    }
+
+    /// Generates a record to write.
+    pub fn next_record(
+        &self,
+        k: &mut Vec<u8>,
+        v: &mut Vec<u8>,
+        rng: &mut impl Rng,
+    ) -> PendingWrite {
+        let x = self.next_ordinal(Access::Write, rng);
+        self.encode(x, k);
+        let expected = self.expected_version(x);
+        if let Some(value) = self.records.as_ref().and_then(|r| r.values.get(x as usize)) {
+            v.clear();
+            v.extend_from_slice(value);
+            return PendingWrite {
+                x,
+                expected,
+                tracked: None,
+            };
+        }
+        // Start the write before its version is drawn, so that a read seeing
+        // no writes in flight cannot miss one with an older version
+        let state = self.writes.get(x as usize).map(|record| {
+            let started = RecordWrites::STARTED + 1;
+            record.state.fetch_add(started, Ordering::SeqCst) + started
+        });
+        let version = self.fill(k, v, rng);
+        PendingWrite {
+            x,
+            expected,
+            tracked: state.zip(version).map(|(state, version)| (version, state)),
         }
     }
 
-    pub fn next_record(&self, k: &mut Vec<u8>, v: &mut Vec<u8>) {
-        self.next(k);
-        v.resize(self.options.vlen, 0);
-        rng().fill(&mut v[..]);
+    /// Records the end of a write, `ok` if it succeeded.
+    pub fn acknowledge_write(&self, write: PendingWrite, ok: bool) {
+        let Some((version, state)) = write.tracked else {
+            return;
+        };
+        let record = &self.writes[write.x as usize];
+        // Clean if no write was in flight when it started and none started
+        // since
+        let clean = ok && state as u32 == 1 && record.state.load(Ordering::SeqCst) == state;
+        let acknowledged = if clean { version + 1 } else { 0 };
+        record.acknowledged.store(acknowledged, Ordering::SeqCst);
+        record.state.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// The version a read of record `x` starting now must return at least,
+    /// if known.
+    pub fn expected_version(&self, x: u64) -> Option<u64> {
+        let record = self.writes.get(x as usize)?;
+        // Writes in flight may or may not have reached the database
+        if record.state.load(Ordering::SeqCst) as u32 != 0 {
+            return None;
+        }
+        record.acknowledged.load(Ordering::SeqCst).checked_sub(1)
+    }
+
+    /// Generates a new record beyond the current keyspace, returns its
+    /// ordinal to `acknowledge_insert` once it is written.
+    pub fn next_insert(&self, k: &mut Vec<u8>, v: &mut Vec<u8>, rng: &mut impl Rng) -> u64 {
+        let x = self.next_insert.fetch_add(1, Ordering::Relaxed);
+        self.encode(x, k);
+        self.fill(k, v, rng);
+        x
+    }
+
+    /// Adds the inserted record `x` to the keyspace once every earlier insert
+    /// finished, as YCSB's acknowledged counter does.
+    pub fn acknowledge_insert(&self, x: u64) {
+        let mut pending = self.pending_inserts.lock().unwrap();
+        pending.insert(x);
+        let mut n = self.num_records.load(Ordering::Relaxed);
+        while pending.remove(&n) {
+            n += 1;
+        }
+        self.num_records.store(n, Ordering::Release);
+    }
+
+    /// Whether keys sort in record order, which keys from a file, hashed and
+    /// prefixed keys do not.
+    pub fn ordered(&self) -> bool {
+        self.records.is_none()
+            && self.options.num_prefixes.is_none()
+            && !matches!(self.options.key_format, KeyFormat::Hashed)
+    }
+
+    /// The number of records in the keyspace.
+    pub fn num_records(&self) -> u64 {
+        self.num_records.load(Ordering::Relaxed)
     }
+
+    /// Encodes the key of record `x`, inserts beyond the keys of a file fall
+    /// back to --key-format.
+    pub fn encode(&self, x: u64, k: &mut Vec<u8>) {
+        let klen = self.options.klen;
+        let prefix = &self.options.key_prefix;
+        k.clear();
+        if let Some(key) = self.records.as_ref().and_then(|r| r.keys.get(x as usize)) {
+            k.extend_from_slice(key);
+            return;
+        }
+        if let Some(num_prefixes) = self.options.num_prefixes {
+            self.encode_prefix(x % num_prefixes, k);
+        }
+        match self.options.key_format {
+            KeyFormat::Binary => encode_binary(x, klen, k),
+            KeyFormat::Decimal => {
+                let width = klen.saturating_sub(prefix.len());
+                write!(k, "{prefix}{x:0width$}").unwrap();
+            }
+            KeyFormat::Hex => write!(k, "{x:0klen$x}").unwrap(),
+            KeyFormat::Hashed => {
+                let width = klen.saturating_sub(prefix.len());
+                let h = FnvBuildHasher::new().hash_one(x);
+                write!(k, "{prefix}{h:0width$}").unwrap();
+            }
+        }
+    }
+
+    fn encode_prefix(&self, p: u64, k: &mut Vec<u8>) {
+        let len = self.options.prefix_len;
+        match self.options.key_format {
+            KeyFormat::Binary => encode_binary(p, len, k),
+            KeyFormat::Decimal | KeyFormat::Hashed => write!(k, "{p:0len$}/").unwrap(),
+            KeyFormat::Hex => write!(k, "{p:0len$x}/").unwrap(),
+        }
+    }
+
+    /// Fills `v` with a value for `k`, returns its version under --verify.
+    fn fill(&self, k: &[u8], v: &mut Vec<u8>, rng: &mut impl Rng) -> Option<u64> {
+        let len = self.value_size.next(rng);
+        if !self.options.verify {
+            v.resize(len, 0);
+            fill_bytes(v, self.options.compression_ratio, rng);
+            return None;
+        }
+        // Layout: version, checksum of version and payload, payload
+        let version = self.version.fetch_add(1, Ordering::Relaxed);
+        v.resize(len.max(VERIFY_HEADER_LEN), 0);
+        let payload = &mut v[VERIFY_HEADER_LEN..];
+        fill_payload(k, version, payload, self.options.compression_ratio);
+        let checksum = checksum(version, payload);
+        v[..8].copy_from_slice(&version.to_le_bytes());
+        v[8..VERIFY_HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+        Some(version)
+    }
+
+    /// Checks a value written with --verify against its key, and that it is
+    /// not older than the `expected` version.
+    pub fn check(&self, k: &[u8], v: &[u8], expected: Option<u64>) -> Result<()> {
+        if v.len() < VERIFY_HEADER_LEN {
+            bail!("value of {} bytes is shorter than its header", v.len());
+        }
+        let version = u64::from_le_bytes(v[..8].try_into().unwrap());
+        let sum = u64::from_le_bytes(v[8..VERIFY_HEADER_LEN].try_into().unwrap());
+        let payload = &v[VERIFY_HEADER_LEN..];
+        if checksum(version, payload) != sum {
+            bail!("checksum mismatch");
+        }
+        let mut buf = vec![0; payload.len()];
+        fill_payload(k, version, &mut buf, self.options.compression_ratio);
+        if buf != payload {
+            bail!("value of version {version} belongs to another key");
+        }
+        if let Some(expected) = expected
+            && version < expected
+        {
+            bail!("stale value of version {version}, expected at least {expected}");
+        }
+        Ok(())
+    }
+
+    pub fn verify(&self) -> bool {
+        self.options.verify
+    }
+}
+
+const VERIFY_HEADER_LEN: usize = 16;
+
+/// Fills `v` with random bytes, repeating a random prefix to make them
+/// compressible to `ratio`, like db_bench.
+fn fill_bytes(v: &mut [u8], ratio: f64, rng: &mut impl Rng) {
+    let n = (v.len() as f64 * ratio).ceil() as usize;
+    let n = n.min(v.len());
+    rng.fill(&mut v[..n]);
+    for i in n..v.len() {
+        v[i] = v[i - n];
+    }
+}
+
+/// Fills the payload of a verifiable value deterministically from the key and
+/// version.
+fn fill_payload(k: &[u8], version: u64, payload: &mut [u8], ratio: f64) {
+    let seed = FnvBuildHasher::new().hash_one((k, version));
+    fill_bytes(payload, ratio, &mut SmallRng::seed_from_u64(seed));
+}
+
+fn checksum(version: u64, payload: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write_u64(version);
+    hasher.write(payload);
+    hasher.finish()
 }
 
-#[derive(Clone, Debug, ValueEnum)]
+/// Appends the big-endian bytes of `x`, truncated or zero-padded to `len`.
+fn encode_binary(x: u64, len: usize, k: &mut Vec<u8>) {
+    let b = x.to_be_bytes();
+    if let Some(i) = b.len().checked_sub(len) {
+        k.extend_from_slice(&b[i..]);
+    } else {
+        k.extend_from_slice(&b);
+        k.resize(k.len() + len - b.len(), 0);
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
 pub enum Distribution {
     Uniform,
+    /// Zipfian with hotspots scattered over the keyspace
     Zipfian,
+    /// Zipfian with hotspots at the start of the keyspace
+    UnscrambledZipfian,
     Sequential,
+    /// Zipfian towards the most recently inserted records
+    Latest,
+    /// A fraction of hot records get most of the accesses
+    Hotspot,
+    /// Exponentially decaying from the most recently inserted records
+    Exponential,
+    /// Gaussian cluster in the keyspace
+    Normal,
 }
 
 impl Distribution {
-    fn new_generator(&self) -> Box<dyn Generator> {
-        match self {
+    fn new_generator(&self, num_records: u64, options: &Options) -> Result<Box<dyn Generator>> {
+        let theta = options.zipf_theta;
+        Ok(match self {
             Self::Uniform => Box::new(UniformGenerator::new()),
-            Self::Zipfian => Box::new(ZipfianGenerator::new()),
+            Self::Zipfian => Box::new(ZipfianGenerator::new(num_records, theta, true)?),
+            Self::UnscrambledZipfian => Box::new(ZipfianGenerator::new(num_records, theta, false)?),
             Self::Sequential => Box::new(SequentialGenerator::new()),
-        }
+            Self::Latest => Box::new(LatestGenerator::new(num_records, theta)?),
+            Self::Hotspot => Box::new(HotspotGenerator::new(
+                num_records,
+                options.hotspot_fraction,
+                options.hotspot_probability,
+            )?),
+            Self::Exponential => Box::new(ExponentialGenerator::new(
+                num_records,
+                options.exponential_percentile,
+                options.exponential_fraction,
+            )?),
+            Self::Normal => Box::new(NormalGenerator::new(
+                num_records,
+                options.normal_mean,
+                options.normal_stddev,
+            )?),
+        })
     }
 }
 
@@ -69,16 +827,54 @@ impl Distribution {
 mod tests {
     use std::collections::BTreeMap;
 
+    use rand::rng;
+
     use super::*;
 
     const NUM_RECORDS: usize = 100;
 
+    fn new_options(klen: usize, distribution: Distribution) -> Options {
+        Options {
+            klen,
+            key_format: KeyFormat::Binary,
+            key_prefix: "user".into(),
+            num_prefixes: None,
+            prefix_len: 4,
+            prefix_distribution: Distribution::Uniform,
+            vlen: 100,
+            vlen_distribution: ValueSizeDistribution::Constant,
+            vlen_min: 1,
+            vlen_max: 1024,
+            vlen_stddev: 10.0,
+            vlen_file: None,
+            compression_ratio: 1.0,
+            verify: false,
+            num_records: NUM_RECORDS,
+            keys_file: None,
+            kv_file: None,
+            kv_file_format: KvFileFormat::Csv,
+            file_encoding: FileEncoding::Raw,
+            distribution,
+            read_distribution: None,
+            write_distribution: None,
+            scan_distribution: None,
+            zipf_theta: 0.99,
+            hotspot_fraction: 0.2,
+            hotspot_probability: 0.8,
+            exponential_percentile: 0.95,
+            exponential_fraction: 0.8571,
+            normal_mean: 0.5,
+            normal_stddev: 0.1,
+        }
+    }
+
     fn test_dataset(options: Options) {
-        let dataset = Dataset::new(options.clone());
+        let dataset = Dataset::new(options.clone()).unwrap();
+        let mut rng = rng();
         let mut k = Vec::new();
         let mut count = BTreeMap::new();
         for _ in 0..(options.num_records * 10) {
-            dataset.next(&mut k);
+            dataset.next(Access::Read, &mut k, &mut rng);
             count.entry(k.clone()).and_modify(|c| *c += 1).or_insert(1);
         }
         println!("{count:#?}");
@@ -87,36 +883,239 @@ mod tests {
     #[test]
     #[ignore]
     fn test_uniform_dataset() {
-        let options = Options {
-            klen: 4,
-            vlen: 100,
-            num_records: NUM_RECORDS,
-            distribution: Distribution::Uniform,
-        };
-        test_dataset(options);
+        test_dataset(new_options(4, Distribution::Uniform));
     }
 
     #[test]
     #[ignore]
     fn test_zipfian_dataset() {
-        let options = Options {
-            klen: 8,
-            vlen: 100,
-            num_records: NUM_RECORDS,
-            distribution: Distribution::Zipfian,
-        };
-        test_dataset(options);
+        test_dataset(new_options(8, Distribution::Zipfian));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_unscrambled_zipfian_dataset() {
+        test_dataset(new_options(8, Distribution::UnscrambledZipfian));
     }
 
     #[test]
     #[ignore]
     fn test_sequential_dataset() {
-        let options = Options {
-            klen: 10,
-            vlen: 100,
-            num_records: NUM_RECORDS,
-            distribution: Distribution::Sequential,
-        };
+        test_dataset(new_options(10, Distribution::Sequential));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_latest_dataset() {
+        test_dataset(new_options(8, Distribution::Latest));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_hotspot_dataset() {
+        test_dataset(new_options(8, Distribution::Hotspot));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exponential_dataset() {
+        test_dataset(new_options(8, Distribution::Exponential));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_normal_dataset() {
+        test_dataset(new_options(8, Distribution::Normal));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_prefixed_dataset() {
+        let mut options = new_options(13, Distribution::Zipfian);
+        options.key_format = KeyFormat::Decimal;
+        options.num_prefixes = Some(4);
+        options.prefix_distribution = Distribution::UnscrambledZipfian;
         test_dataset(options);
     }
+
+    #[test]
+    fn test_key_format() {
+        let mut options = new_options(13, Distribution::Uniform);
+        let mut k = Vec::new();
+        options.key_format = KeyFormat::Decimal;
+        Dataset::new(options.clone()).unwrap().encode(1234, &mut k);
+        assert_eq!(k, b"user000001234");
+        options.key_format = KeyFormat::Hex;
+        Dataset::new(options.clone()).unwrap().encode(0xabc, &mut k);
+        assert_eq!(k, b"0000000000abc");
+        options.key_format = KeyFormat::Hashed;
+        Dataset::new(options).unwrap().encode(1234, &mut k);
+        assert!(k.starts_with(b"user"));
+        options = new_options(4, Distribution::Uniform);
+        Dataset::new(options).unwrap().encode(0x01020304, &mut k);
+        assert_eq!(k, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut options = new_options(8, Distribution::Uniform);
+        options.verify = true;
+        options.compression_ratio = 0.5;
+        let dataset = Dataset::new(options).unwrap();
+        let mut rng = rng();
+        let (mut k, mut v) = (Vec::new(), Vec::new());
+        let write = dataset.next_record(&mut k, &mut v, &mut rng);
+        dataset.check(&k, &v, None).unwrap();
+        let mut other = Vec::new();
+        dataset.encode(
+            u64::from_be_bytes(k[..8].try_into().unwrap()) + 1,
+            &mut other,
+        );
+        assert!(dataset.check(&other, &v, None).is_err());
+        let x = write.x;
+        dataset.acknowledge_write(write, true);
+        let expected = dataset.expected_version(x);
+        assert!(expected.is_some());
+        dataset.check(&k, &v, expected).unwrap();
+        // A later write of the record makes the first value stale
+        let mut newer = Vec::new();
+        let mut write = dataset.next_record(&mut other, &mut newer, &mut rng);
+        while write.x != x {
+            dataset.acknowledge_write(write, true);
+            write = dataset.next_record(&mut other, &mut newer, &mut rng);
+        }
+        assert_eq!(dataset.expected_version(x), None);
+        dataset.acknowledge_write(write, true);
+        assert!(dataset.check(&k, &v, dataset.expected_version(x)).is_err());
+        dataset
+            .check(&k, &newer, dataset.expected_version(x))
+            .unwrap();
+        v[VERIFY_HEADER_LEN] ^= 1;
+        assert!(dataset.check(&k, &v, None).is_err());
+    }
+
+    #[test]
+    fn test_overlapping_writes() {
+        let mut options = new_options(8, Distribution::Uniform);
+        options.verify = true;
+        options.num_records = 1;
+        let dataset = Dataset::new(options).unwrap();
+        let mut rng = rng();
+        let (mut k, mut v) = (Vec::new(), Vec::new());
+        let first = dataset.next_record(&mut k, &mut v, &mut rng);
+        let second = dataset.next_record(&mut k, &mut v, &mut rng);
+        dataset.acknowledge_write(second, true);
+        dataset.acknowledge_write(first, true);
+        // Either write may have reached the database last
+        assert_eq!(dataset.expected_version(0), None);
+        let write = dataset.next_record(&mut k, &mut v, &mut rng);
+        dataset.acknowledge_write(write, false);
+        assert_eq!(dataset.expected_version(0), None);
+        let write = dataset.next_record(&mut k, &mut v, &mut rng);
+        dataset.acknowledge_write(write, true);
+        assert!(dataset.expected_version(0).is_some());
+    }
+
+    #[test]
+    fn test_distribution_range() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for distribution in Distribution::value_variants() {
+            let dataset = Dataset::new(new_options(8, distribution.clone())).unwrap();
+            for access in [Access::Read, Access::Write, Access::Scan] {
+                for _ in 0..(NUM_RECORDS * 100) {
+                    let x = dataset.next_ordinal(access, &mut rng);
+                    assert!(x < NUM_RECORDS as u64, "{distribution:?} generated {x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let mut options = new_options(8, Distribution::Zipfian);
+        options.num_records = 0;
+        assert!(Dataset::new(options).is_err());
+        let mut options = new_options(8, Distribution::Normal);
+        options.normal_stddev = f64::INFINITY;
+        assert!(Dataset::new(options).is_err());
+        let mut options = new_options(8, Distribution::Uniform);
+        options.verify = true;
+        options.kv_file = Some("kvs.csv".into());
+        assert!(Dataset::new(options).is_err());
+        for ratio in [0.0, 1.5, f64::NAN] {
+            let mut options = new_options(8, Distribution::Uniform);
+            options.compression_ratio = ratio;
+            assert!(Dataset::new(options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_acknowledge_insert() {
+        let dataset = Dataset::new(new_options(8, Distribution::Uniform)).unwrap();
+        let mut rng = rng();
+        let (mut k, mut v) = (Vec::new(), Vec::new());
+        let n = NUM_RECORDS as u64;
+        let first = dataset.next_insert(&mut k, &mut v, &mut rng);
+        let second = dataset.next_insert(&mut k, &mut v, &mut rng);
+        assert_eq!((first, second), (n, n + 1));
+        assert_eq!(dataset.num_records(), n);
+        dataset.acknowledge_insert(second);
+        assert_eq!(dataset.num_records(), n);
+        dataset.acknowledge_insert(first);
+        assert_eq!(dataset.num_records(), n + 2);
+    }
+
+    fn write_temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dbbench-{}-{name}", std::process::id()));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_file_encoding() {
+        assert_eq!(FileEncoding::Raw.decode(b" a,b ").unwrap(), b" a,b ");
+        assert_eq!(FileEncoding::Hex.decode(b" 00aF\t").unwrap(), [0x00, 0xaf]);
+        assert_eq!(FileEncoding::Hex.decode(b"").unwrap(), b"");
+        assert!(FileEncoding::Hex.decode(b"abc").is_err());
+        assert!(FileEncoding::Hex.decode(b"zz").is_err());
+        assert!(FileEncoding::Hex.decode(b"+1").is_err());
+    }
+
+    #[test]
+    fn test_read_field() {
+        let mut data: &[u8] = &[2, 0, 0, 0, b'a', b'b', 0, 0, 0, 0];
+        assert_eq!(read_field(&mut data).unwrap(), b"ab");
+        assert_eq!(read_field(&mut data).unwrap(), b"");
+        assert!(data.is_empty());
+        assert!(read_field(&mut &[1, 0, 0][..]).is_err());
+        assert!(read_field(&mut &[3, 0, 0, 0, b'a', b'b'][..]).is_err());
+    }
+
+    #[test]
+    fn test_read_kvs() {
+        let mut options = new_options(8, Distribution::Uniform);
+        let path = write_temp_file("kvs.csv", b"k1,v1\r\n\nk2,v2,more\n");
+        let records = Records::read_kvs(&path, &options).unwrap();
+        assert_eq!(records.keys, [b"k1".to_vec(), b"k2".to_vec()]);
+        assert_eq!(records.values, [b"v1".to_vec(), b"v2,more".to_vec()]);
+        fs::write(&path, b"k1\n").unwrap();
+        assert!(Records::read_kvs(&path, &options).is_err());
+
+        options.file_encoding = FileEncoding::Hex;
+        fs::write(&path, b"6b31,00ff\n").unwrap();
+        let records = Records::read_kvs(&path, &options).unwrap();
+        assert_eq!(records.keys, [b"k1".to_vec()]);
+        assert_eq!(records.values, [vec![0x00, 0xff]]);
+        fs::remove_file(path).unwrap();
+
+        options.kv_file_format = KvFileFormat::Binary;
+        let path = write_temp_file("kvs.bin", &[2, 0, 0, 0, b'k', b'1', 1, 0, 0, 0, b'\n']);
+        let records = Records::read_kvs(&path, &options).unwrap();
+        assert_eq!(records.keys, [b"k1".to_vec()]);
+        assert_eq!(records.values, [b"\n".to_vec()]);
+        // A key without its value
+        fs::write(&path, [2, 0, 0, 0, b'k', b'1']).unwrap();
+        assert!(Records::read_kvs(&path, &options).is_err());
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -1,9 +1,9 @@
-use std::{hint::black_box, ops::Bound};
+use std::ops::Bound;
 
 use anyhow::Result;
 use heed::{Env, EnvFlags, EnvOpenOptions, types::Bytes};
 
-use crate::db::{Database, Options};
+use crate::db::{Database, Options, modify};
 
 pub struct Lmdb {
     env: Env,
@@ -34,22 +34,89 @@ impl Database for Lmdb {
         Ok(format!("{stat:#?}"))
     }
 
-    fn read(&self, k: &[u8]) -> Result<bool> {
+    fn read(&self, k: &[u8], v: &mut Vec<u8>) -> Result<bool> {
         let txn = self.env.read_txn()?;
-        let found = self.db.get(&txn, k)?.is_some();
+        let found = match self.db.get(&txn, k)? {
+            Some(value) => {
+                v.clear();
+                v.extend_from_slice(value);
+                true
+            }
+            None => false,
+        };
         txn.commit()?;
         Ok(found)
     }
 
-    fn scan(&self, k: &[u8], n: usize) -> Result<()> {
+    fn multi_get(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let txn = self.env.read_txn()?;
+        let mut values = Vec::with_capacity(keys.len());
+        for k in keys {
+            values.push(self.db.get(&txn, k)?.map(|v| v.to_vec()));
+        }
+        txn.commit()?;
+        Ok(values)
+    }
+
+    fn scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)> {
         let txn = self.env.read_txn()?;
         let range = (Bound::Included(k), Bound::Unbounded);
-        black_box({
-            let mut iter = self.db.range(&txn, &range)?;
-            for _ in 0..n {
-                iter.next();
-            }
-        });
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in self.db.range(&txn, &range)?.take(n) {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        txn.commit()?;
+        Ok((rows, bytes))
+    }
+
+    fn reverse_scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)> {
+        let txn = self.env.read_txn()?;
+        let range = (Bound::Unbounded, Bound::Included(k));
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in self.db.rev_range(&txn, &range)?.take(n) {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        txn.commit()?;
+        Ok((rows, bytes))
+    }
+
+    fn range_scan(&self, start: &[u8], end: &[u8]) -> Result<(usize, usize)> {
+        let txn = self.env.read_txn()?;
+        let range = (Bound::Included(start), Bound::Excluded(end));
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in self.db.range(&txn, &range)? {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        txn.commit()?;
+        Ok((rows, bytes))
+    }
+
+    fn prefix_scan(&self, prefix: &[u8]) -> Result<usize> {
+        let txn = self.env.read_txn()?;
+        let mut bytes = 0;
+        for kv in self.db.prefix_iter(&txn, prefix)? {
+            let (k, v) = kv?;
+            bytes += k.len() + v.len();
+        }
+        txn.commit()?;
+        Ok(bytes)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<()> {
+        let txn = self.env.read_txn()?;
+        for kv in self.db.iter(&txn)? {
+            let (k, v) = kv?;
+            f(k, v);
+        }
         txn.commit()?;
         Ok(())
     }
@@ -60,4 +127,29 @@ impl Database for Lmdb {
         txn.commit()?;
         Ok(())
     }
+
+    fn write_batch(&self, kvs: &[(&[u8], &[u8])]) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        for (k, v) in kvs {
+            self.db.put(&mut txn, k, v)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, k: &[u8]) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        self.db.delete(&mut txn, k)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn read_modify_write(&self, k: &[u8], v: &[u8]) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        let mut value = self.db.get(&txn, k)?.unwrap_or_default().to_vec();
+        modify(&mut value, v);
+        self.db.put(&mut txn, k, &value)?;
+        txn.commit()?;
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use clap::{Args, ValueEnum};
+use serde::Serialize;
 
 mod lmdb;
 use lmdb::Lmdb;
@@ -7,13 +8,13 @@ use lmdb::Lmdb;
 mod rocksdb;
 use rocksdb::Rocksdb;
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Debug, ValueEnum, Serialize)]
 pub enum Name {
     Lmdb,
     Rocksdb,
 }
 
-#[derive(Clone, Debug, Args)]
+#[derive(Clone, Debug, Args, Serialize)]
 pub struct Options {
     pub db: Name,
     pub path: String,
@@ -21,6 +22,24 @@ pub struct Options {
     pub sync: bool,
     #[arg(long, default_value_t = 128 * 1024 * 1024)]
     pub cache_size: usize,
+    /// The compression of all levels (RocksDB only)
+    #[arg(long, value_enum, default_value_t = Compression::None)]
+    pub compression: Compression,
+    /// The compression of each level, overrides --compression (RocksDB only)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub compression_per_level: Vec<Compression>,
+    /// Build prefix bloom filters over the first N key bytes, e.g. the
+    /// prefix component of --num-prefixes (RocksDB only)
+    #[arg(long)]
+    pub prefix_extractor_len: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize)]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
 }
 
 impl Options {
@@ -42,9 +61,48 @@ impl Options {
 pub trait Database: Send + Sync {
     fn stat(&self) -> Result<String>;
 
-    fn read(&self, k: &[u8]) -> Result<bool>;
+    /// Reads the value of `k` into `v`, returns whether it was found.
+    fn read(&self, k: &[u8], v: &mut Vec<u8>) -> Result<bool>;
+
+    /// Returns the values of `keys` in order.
+    fn multi_get(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>>;
+
+    /// Returns the number of rows and key and value bytes scanned.
+    fn scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)>;
+
+    /// Scans backwards from the last key at or before `k`, returns the number
+    /// of rows and key and value bytes scanned.
+    fn reverse_scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)>;
+
+    /// Returns the number of rows and key and value bytes of all keys in
+    /// `[start, end)`.
+    fn range_scan(&self, start: &[u8], end: &[u8]) -> Result<(usize, usize)>;
+
+    /// Returns the number of key and value bytes of all keys starting with
+    /// `prefix`.
+    fn prefix_scan(&self, prefix: &[u8]) -> Result<usize>;
 
-    fn scan(&self, k: &[u8], n: usize) -> Result<()>;
+    /// Calls `f` with every key and value in order.
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<()>;
 
     fn write(&self, k: &[u8], v: &[u8]) -> Result<()>;
+
+    /// Writes all `kvs` atomically.
+    fn write_batch(&self, kvs: &[(&[u8], &[u8])]) -> Result<()>;
+
+    fn delete(&self, k: &[u8]) -> Result<()>;
+
+    /// Reads the value of `k` and `modify`s it with `v` atomically.
+    fn read_modify_write(&self, k: &[u8], v: &[u8]) -> Result<()>;
+}
+
+/// The modification of read-modify-writes, XORs `operand` into `value`,
+/// extending it with zeros to the length of `operand`.
+pub fn modify(value: &mut Vec<u8>, operand: &[u8]) {
+    if value.len() < operand.len() {
+        value.resize(operand.len(), 0);
+    }
+    for (a, b) in value.iter_mut().zip(operand) {
+        *a ^= b;
+    }
 }
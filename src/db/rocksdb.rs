@@ -1,12 +1,12 @@
-use std::hint::black_box;
-
 use anyhow::Result;
 use rocksdb::{
-    BlockBasedOptions, Cache, DB, DBCompressionType, IteratorMode, Options as DbOptions,
-    ReadOptions, WriteOptions,
+    BlockBasedOptions, Cache, DB, DBCompressionType, IteratorMode, MergeOperands,
+    Options as DbOptions, ReadOptions, SliceTransform, WriteBatch, WriteOptions,
 };
 
-use crate::db::{Database, Options};
+use crate::db::{Compression, Database, Options, modify};
+
+const DEFAULT_COLUMN_FAMILY: &str = "default";
 
 pub struct Rocksdb {
     db: DB,
@@ -34,11 +34,27 @@ impl Rocksdb {
         dbopts.set_avoid_unnecessary_blocking_io(true);
         dbopts.set_block_based_table_factory(&topts);
         dbopts.optimize_level_style_compaction(write_buffer_size);
-        // The above function enables compression, we disable it here.
-        dbopts.set_compression_type(DBCompressionType::None);
-        dbopts.set_compression_per_level(&[DBCompressionType::None; 7]);
+        // The above function enables compression, we override it here.
+        let compression = compression_type(options.compression);
+        let compression_per_level = if options.compression_per_level.is_empty() {
+            vec![compression; 7]
+        } else {
+            options
+                .compression_per_level
+                .iter()
+                .map(|&c| compression_type(c))
+                .collect()
+        };
+        dbopts.set_compression_type(compression);
+        dbopts.set_compression_per_level(&compression_per_level);
         dbopts.increase_parallelism(num_background_threads);
-        let db = DB::open(&dbopts, options.path)?;
+        dbopts.set_merge_operator_associative("modify", modify_merge);
+        if let Some(len) = options.prefix_extractor_len {
+            dbopts.set_prefix_extractor(SliceTransform::create_fixed_prefix(len));
+            dbopts.set_memtable_prefix_bloom_ratio(0.1);
+        }
+        // Open the default column family explicitly for batched multi-gets
+        let db = DB::open_cf(&dbopts, options.path, [DEFAULT_COLUMN_FAMILY])?;
         Ok(Self { db, ropts, wopts })
     }
 }
@@ -49,22 +65,107 @@ impl Database for Rocksdb {
         Ok(stat.unwrap_or_default())
     }
 
-    fn read(&self, k: &[u8]) -> Result<bool> {
-        let value = self.db.get_pinned_opt(k, &self.ropts)?;
-        Ok(value.is_some())
+    fn read(&self, k: &[u8], v: &mut Vec<u8>) -> Result<bool> {
+        let Some(value) = self.db.get_pinned_opt(k, &self.ropts)? else {
+            return Ok(false);
+        };
+        v.clear();
+        v.extend_from_slice(&value);
+        Ok(true)
     }
 
-    fn scan(&self, k: &[u8], n: usize) -> Result<()> {
+    fn multi_get(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let cf = self.db.cf_handle(DEFAULT_COLUMN_FAMILY).unwrap();
+        self.db
+            .batched_multi_get_cf_opt(cf, keys.iter().copied(), false, &self.ropts)
+            .into_iter()
+            .map(|v| Ok(v?.map(|v| v.to_vec())))
+            .collect()
+    }
+
+    fn scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)> {
         let mut ropts = ReadOptions::default();
         ropts.fill_cache(true);
-        let mut iter = self
+        // Don't stop at the end of the prefix with a prefix extractor
+        ropts.set_total_order_seek(true);
+        let iter = self
             .db
             .iterator_opt(IteratorMode::From(k, rocksdb::Direction::Forward), ropts);
-        black_box({
-            for _ in 0..n {
-                iter.next();
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in iter.take(n) {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        Ok((rows, bytes))
+    }
+
+    fn reverse_scan(&self, k: &[u8], n: usize) -> Result<(usize, usize)> {
+        let mut ropts = ReadOptions::default();
+        ropts.fill_cache(true);
+        ropts.set_total_order_seek(true);
+        let iter = self
+            .db
+            .iterator_opt(IteratorMode::From(k, rocksdb::Direction::Reverse), ropts);
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in iter.take(n) {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        Ok((rows, bytes))
+    }
+
+    fn range_scan(&self, start: &[u8], end: &[u8]) -> Result<(usize, usize)> {
+        let mut ropts = ReadOptions::default();
+        ropts.fill_cache(true);
+        ropts.set_total_order_seek(true);
+        ropts.set_iterate_upper_bound(end);
+        let iter = self.db.iterator_opt(
+            IteratorMode::From(start, rocksdb::Direction::Forward),
+            ropts,
+        );
+        let mut rows = 0;
+        let mut bytes = 0;
+        for kv in iter {
+            let (k, v) = kv?;
+            rows += 1;
+            bytes += k.len() + v.len();
+        }
+        Ok((rows, bytes))
+    }
+
+    fn prefix_scan(&self, prefix: &[u8]) -> Result<usize> {
+        let mut ropts = ReadOptions::default();
+        ropts.fill_cache(true);
+        // Use the prefix bloom filters if the prefix extractor matches
+        ropts.set_prefix_same_as_start(true);
+        let iter = self.db.iterator_opt(
+            IteratorMode::From(prefix, rocksdb::Direction::Forward),
+            ropts,
+        );
+        let mut bytes = 0;
+        for kv in iter {
+            let (k, v) = kv?;
+            if !k.starts_with(prefix) {
+                break;
             }
-        });
+            bytes += k.len() + v.len();
+        }
+        Ok(bytes)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<()> {
+        let mut ropts = ReadOptions::default();
+        // Don't pollute the block cache with a full scan
+        ropts.fill_cache(false);
+        ropts.set_total_order_seek(true);
+        for kv in self.db.iterator_opt(IteratorMode::Start, ropts) {
+            let (k, v) = kv?;
+            f(&k, &v);
+        }
         Ok(())
     }
 
@@ -72,4 +173,43 @@ impl Database for Rocksdb {
         self.db.put_opt(k, v, &self.wopts)?;
         Ok(())
     }
+
+    fn write_batch(&self, kvs: &[(&[u8], &[u8])]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for (k, v) in kvs {
+            batch.put(k, v);
+        }
+        self.db.write_opt(batch, &self.wopts)?;
+        Ok(())
+    }
+
+    fn delete(&self, k: &[u8]) -> Result<()> {
+        self.db.delete_opt(k, &self.wopts)?;
+        Ok(())
+    }
+
+    fn read_modify_write(&self, k: &[u8], v: &[u8]) -> Result<()> {
+        // The read is deferred to the merge operator, which resolves it on
+        // the next get or compaction.
+        self.db.merge_opt(k, v, &self.wopts)?;
+        Ok(())
+    }
+}
+
+fn compression_type(compression: Compression) -> DBCompressionType {
+    match compression {
+        Compression::None => DBCompressionType::None,
+        Compression::Snappy => DBCompressionType::Snappy,
+        Compression::Lz4 => DBCompressionType::Lz4,
+        Compression::Zstd => DBCompressionType::Zstd,
+    }
+}
+
+// Associative since XOR is, so it also combines operands without a value
+fn modify_merge(_: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    let mut value = existing.unwrap_or_default().to_vec();
+    for operand in operands.iter() {
+        modify(&mut value, operand);
+    }
+    Some(value)
 }
@@ -3,12 +3,13 @@ use std::{
     sync::atomic::{AtomicU64, Ordering},
 };
 
+use anyhow::{Result, bail};
 use fnv::FnvBuildHasher;
-use rand::{Rng, rng};
-use rand_distr::{StandardUniform, Zipf};
+use rand::{Rng, RngCore, distr::Uniform};
+use rand_distr::{Exp, Normal, StandardUniform, Zipf};
 
 pub trait Generator: Send + Sync {
-    fn next(&self) -> u64;
+    fn next(&self, rng: &mut dyn RngCore) -> u64;
 }
 
 pub struct UniformGenerator {
@@ -24,30 +25,144 @@ impl UniformGenerator {
 }
 
 impl Generator for UniformGenerator {
-    fn next(&self) -> u64 {
-        rng().sample(self.dist)
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        rng.sample(self.dist)
     }
 }
 
+/// Generates `0..num_items` with a zipfian skew of `theta`.
+///
+/// If `scrambled`, the hotspots are scattered over the whole range, otherwise
+/// the smallest numbers are the hottest.
 pub struct ZipfianGenerator {
     dist: Zipf<f64>,
-    hash: FnvBuildHasher,
+    num_items: u64,
+    hash: Option<FnvBuildHasher>,
 }
 
 impl ZipfianGenerator {
-    pub fn new() -> Self {
-        Self {
-            dist: Zipf::new(u64::MAX as f64, 0.99).unwrap(),
-            hash: FnvBuildHasher::new(),
-        }
+    pub fn new(num_items: u64, theta: f64, scrambled: bool) -> Result<Self> {
+        Ok(Self {
+            dist: Zipf::new(num_items as f64, theta)?,
+            num_items,
+            hash: scrambled.then(FnvBuildHasher::new),
+        })
     }
 }
 
 impl Generator for ZipfianGenerator {
-    fn next(&self) -> u64 {
-        let x = rng().sample(self.dist) as u64;
-        // Scatter hotspots
-        self.hash.hash_one(x)
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        // Zipf samples start from 1
+        let x = rng.sample(self.dist) as u64 - 1;
+        match &self.hash {
+            // Scatter hotspots
+            Some(hash) => hash.hash_one(x) % self.num_items,
+            None => x,
+        }
+    }
+}
+
+/// Generates offsets back from the latest item with a zipfian skew.
+pub struct LatestGenerator {
+    dist: Zipf<f64>,
+}
+
+impl LatestGenerator {
+    pub fn new(num_items: u64, theta: f64) -> Result<Self> {
+        Ok(Self {
+            dist: Zipf::new(num_items as f64, theta)?,
+        })
+    }
+}
+
+impl Generator for LatestGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        // Zipf samples start from 1
+        rng.sample(self.dist) as u64 - 1
+    }
+}
+
+/// Generates `0..num_items` where a `hot_fraction` of the items are accessed
+/// with `hot_probability`.
+pub struct HotspotGenerator {
+    num_items: u64,
+    num_hot_items: u64,
+    hot_probability: f64,
+}
+
+impl HotspotGenerator {
+    pub fn new(num_items: u64, hot_fraction: f64, hot_probability: f64) -> Result<Self> {
+        if num_items == 0 {
+            bail!("no items to generate");
+        }
+        if !(0.0..=1.0).contains(&hot_probability) {
+            bail!("hot probability {hot_probability} is not between 0 and 1");
+        }
+        let num_hot_items = ((num_items as f64 * hot_fraction) as u64).clamp(1, num_items);
+        Ok(Self {
+            num_items,
+            num_hot_items,
+            hot_probability,
+        })
+    }
+}
+
+impl Generator for HotspotGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        if self.num_hot_items == self.num_items || rng.random_bool(self.hot_probability) {
+            rng.random_range(0..self.num_hot_items)
+        } else {
+            rng.random_range(self.num_hot_items..self.num_items)
+        }
+    }
+}
+
+/// Generates offsets back from the latest item with an exponential decay,
+/// such that a `percentile` of the offsets fall within the first `fraction`
+/// of the items.
+pub struct ExponentialGenerator {
+    dist: Exp<f64>,
+}
+
+impl ExponentialGenerator {
+    pub fn new(num_items: u64, percentile: f64, fraction: f64) -> Result<Self> {
+        let lambda = -(1.0 - percentile).ln() / (num_items as f64 * fraction);
+        Ok(Self {
+            dist: Exp::new(lambda)?,
+        })
+    }
+}
+
+impl Generator for ExponentialGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        rng.sample(self.dist) as u64
+    }
+}
+
+/// Generates `0..num_items` clustered around `mean` with `stddev`, both as
+/// fractions of the items.
+pub struct NormalGenerator {
+    dist: Normal<f64>,
+    num_items: u64,
+}
+
+impl NormalGenerator {
+    pub fn new(num_items: u64, mean: f64, stddev: f64) -> Result<Self> {
+        if num_items == 0 {
+            bail!("no items to generate");
+        }
+        let n = num_items as f64;
+        Ok(Self {
+            dist: Normal::new(n * mean, n * stddev)?,
+            num_items,
+        })
+    }
+}
+
+impl Generator for NormalGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        let x = rng.sample(self.dist).max(0.0) as u64;
+        x.min(self.num_items - 1)
     }
 }
 
@@ -64,23 +179,74 @@ impl SequentialGenerator {
 }
 
 impl Generator for SequentialGenerator {
-    fn next(&self) -> u64 {
+    fn next(&self, _: &mut dyn RngCore) -> u64 {
         self.count.fetch_add(1, Ordering::Relaxed)
     }
 }
 
+/// Generates value sizes or scan lengths from `min` to `max`, favoring
+/// smaller ones if zipfian.
+pub enum SizeGenerator {
+    Constant(usize),
+    Uniform(Uniform<usize>),
+    Zipfian(usize, Zipf<f64>),
+}
+
+impl SizeGenerator {
+    pub fn uniform(min: usize, max: usize) -> Result<Self> {
+        check_size_range(min, max)?;
+        Ok(Self::Uniform(Uniform::new_inclusive(min, max)?))
+    }
+
+    pub fn zipfian(min: usize, max: usize) -> Result<Self> {
+        check_size_range(min, max)?;
+        Ok(Self::Zipfian(min, Zipf::new((max - min + 1) as f64, 0.99)?))
+    }
+
+    pub fn next(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            Self::Constant(n) => *n,
+            Self::Uniform(dist) => rng.sample(dist),
+            // Zipf samples start from 1
+            Self::Zipfian(min, dist) => min + rng.sample(dist) as usize - 1,
+        }
+    }
+}
+
+/// Checks that sizes can range from `min` to `max`.
+pub fn check_size_range(min: usize, max: usize) -> Result<()> {
+    if min > max {
+        bail!("the minimum {min} exceeds the maximum {max}");
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
 
+    use rand::{SeedableRng, rng, rngs::SmallRng};
+
     use super::*;
 
     const MAX: u64 = 100;
+    const SAMPLES: usize = 100_000;
+
+    fn samples(generator: &impl Generator) -> Vec<u64> {
+        let mut rng = SmallRng::seed_from_u64(0);
+        (0..SAMPLES).map(|_| generator.next(&mut rng)).collect()
+    }
+
+    /// The share of `samples` below `bound`.
+    fn share_below(samples: &[u64], bound: u64) -> f64 {
+        samples.iter().filter(|&&x| x < bound).count() as f64 / samples.len() as f64
+    }
 
     fn test_generator<G: Generator>(generator: G) {
+        let mut rng = rng();
         let mut count = BTreeMap::new();
         for _ in 0..(MAX * 10) {
-            let x = generator.next() % MAX;
+            let x = generator.next(&mut rng) % MAX;
             count.entry(x).and_modify(|c| *c += 1).or_insert(1);
         }
         println!("{count:#?}");
@@ -95,7 +261,37 @@ mod tests {
     #[test]
     #[ignore]
     fn test_zipfian_generator() {
-        test_generator(ZipfianGenerator::new());
+        test_generator(ZipfianGenerator::new(MAX, 0.99, true).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_unscrambled_zipfian_generator() {
+        test_generator(ZipfianGenerator::new(MAX, 0.99, false).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_latest_generator() {
+        test_generator(LatestGenerator::new(MAX, 0.99).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_hotspot_generator() {
+        test_generator(HotspotGenerator::new(MAX, 0.2, 0.8).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exponential_generator() {
+        test_generator(ExponentialGenerator::new(MAX, 0.95, 0.8571).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_normal_generator() {
+        test_generator(NormalGenerator::new(MAX, 0.5, 0.1).unwrap());
     }
 
     #[test]
@@ -103,4 +299,60 @@ mod tests {
     fn test_sequential_generator() {
         test_generator(SequentialGenerator::new());
     }
+
+    #[test]
+    fn test_zipfian_range() {
+        for scrambled in [true, false] {
+            let generator = ZipfianGenerator::new(MAX, 0.99, scrambled).unwrap();
+            assert!(samples(&generator).iter().all(|&x| x < MAX));
+        }
+        let generator = LatestGenerator::new(MAX, 0.99).unwrap();
+        assert!(samples(&generator).iter().all(|&x| x < MAX));
+    }
+
+    #[test]
+    fn test_hotspot_hit_rate() {
+        let generator = HotspotGenerator::new(MAX, 0.2, 0.8).unwrap();
+        let samples = samples(&generator);
+        assert!(samples.iter().all(|&x| x < MAX));
+        assert!((share_below(&samples, 20) - 0.8).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_exponential_percentile() {
+        let generator = ExponentialGenerator::new(MAX, 0.95, 0.5).unwrap();
+        assert!((share_below(&samples(&generator), 50) - 0.95).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_normal_range() {
+        let generator = NormalGenerator::new(MAX, 0.5, 1.0).unwrap();
+        let samples = samples(&generator);
+        assert!(samples.iter().all(|&x| x < MAX));
+        assert!((share_below(&samples, 50) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_invalid_generator() {
+        assert!(ZipfianGenerator::new(0, 0.99, true).is_err());
+        assert!(HotspotGenerator::new(0, 0.2, 0.8).is_err());
+        assert!(HotspotGenerator::new(MAX, 0.2, 1.5).is_err());
+        assert!(NormalGenerator::new(0, 0.5, 0.1).is_err());
+        assert!(NormalGenerator::new(MAX, 0.5, f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_size_generator() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for generator in [
+            SizeGenerator::uniform(10, 20).unwrap(),
+            SizeGenerator::zipfian(10, 20).unwrap(),
+        ] {
+            for _ in 0..SAMPLES {
+                assert!((10..=20).contains(&generator.next(&mut rng)));
+            }
+        }
+        assert!(SizeGenerator::uniform(20, 10).is_err());
+        assert!(SizeGenerator::zipfian(20, 10).is_err());
+    }
 }
@@ -1,20 +1,26 @@
-use std::io::stdin;
+use std::{io::stdin, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use clap::{Args, Parser, Subcommand};
+use fnv::FnvHashSet;
+use serde::Serialize;
 
 mod db;
 mod generator;
+mod report;
+use report::Reporter;
 
 mod runtime;
 use runtime::Runtime;
 
 mod dataset;
-use dataset::Dataset;
+use dataset::{Dataset, Distribution};
 
 mod workload;
 use workload::Workload;
 
+mod trace;
+
 #[derive(Parser)]
 #[command(about, version)]
 struct Cli {
@@ -30,9 +36,14 @@ enum Command {
     Load(LoadCommand),
     /// Open a database for inspection
     Open(OpenCommand),
+    /// Check every record of a `load --verify` given the same dataset options,
+    /// or only the keys of a load from a file
+    Verify(VerifyCommand),
+    /// Replay a trace recorded by `run --record-trace`
+    Replay(ReplayCommand),
 }
 
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Serialize)]
 struct RunCommand {
     #[command(flatten)]
     db: db::Options,
@@ -40,18 +51,49 @@ struct RunCommand {
     dataset: dataset::Options,
     #[command(flatten)]
     workload: workload::Options,
-    #[arg(long, short = 't', default_value_t = 1)]
-    num_threads: usize,
-    #[arg(long, short = 'o', default_value_t = 1_000_000)]
-    num_operations: usize,
+    #[command(flatten)]
+    output: report::Options,
+    #[command(flatten)]
+    runtime: runtime::Options,
+    /// Use a YCSB core workload, overriding the operation mix and
+    /// distribution
+    #[arg(long, value_enum)]
+    preset: Option<workload::Preset>,
 }
 
 impl RunCommand {
+    fn apply_preset(&mut self) {
+        if let Some(preset) = self.preset {
+            preset.apply(&mut self.workload, &mut self.dataset);
+        }
+    }
+
     fn run(self) -> Result<()> {
+        let dataset = Dataset::new(self.dataset.clone())?;
+        self.run_dataset(dataset)
+    }
+
+    fn run_dataset(mut self, dataset: Dataset) -> Result<()> {
+        if self.workload.prefix_scan_ratio > 0.0 && self.dataset.num_prefixes.is_none() {
+            bail!("--prefix-scan-ratio requires --num-prefixes");
+        }
+        if self.workload.range_scan_ratio > 0.0 && !dataset.ordered() {
+            bail!("--range-scan-ratio requires ordered keys, not hashed, prefixed or file keys");
+        }
+        if self.workload.native_rmw && self.dataset.verify {
+            bail!("--native-rmw cannot be used with --verify, merged values are not verifiable");
+        }
+        // Report the number of records read from a file
+        self.dataset.num_records = dataset.num_records() as usize;
+        let seed = *self.runtime.seed.get_or_insert_with(rand::random);
+        let reporter = Reporter::new(&self.output, &self)?;
+        if let Some(preset) = self.preset {
+            reporter.note(&format!("Preset: YCSB workload {preset:?}"))?;
+        }
+        reporter.note(&format!("Seed: {seed}"))?;
         let db = self.db.open()?;
-        let dataset = Dataset::new(self.dataset);
-        let workload = Workload::new(self.workload);
-        Runtime::new(db, dataset, workload).run(self.num_threads, self.num_operations)
+        let workload = Workload::new(self.workload)?;
+        Runtime::new(db, dataset, workload, reporter).run(self.runtime)
     }
 }
 
@@ -61,21 +103,38 @@ struct LoadCommand {
     db: db::Options,
     #[command(flatten)]
     dataset: dataset::Options,
+    #[command(flatten)]
+    output: report::Options,
     #[arg(long, short = 't', default_value_t = 1)]
     num_threads: usize,
+    /// Group this many writes into one atomic batch
+    #[arg(long, default_value_t = 1)]
+    batch_size: usize,
+    /// Seed the per-thread random generators for reproducible values
+    /// [default: random]
+    #[arg(long)]
+    seed: Option<u64>,
 }
 
 impl LoadCommand {
-    fn run(self) -> Result<()> {
-        let num_operations = self.dataset.num_records;
+    fn run(mut self) -> Result<()> {
+        // Verification and file records expect every record, so write each
+        // once in order
+        if self.dataset.verify || self.dataset.keys_file.is_some() || self.dataset.kv_file.is_some()
+        {
+            self.dataset.write_distribution = Some(Distribution::Sequential);
+        }
+        let dataset = Dataset::new(self.dataset.clone())?;
+        let num_operations = dataset.num_records() as usize;
         let cmd = RunCommand {
             db: self.db,
             dataset: self.dataset,
-            workload: workload::Options::new_for_load(),
-            num_threads: self.num_threads,
-            num_operations,
+            workload: workload::Options::new_for_load(self.batch_size),
+            output: self.output,
+            runtime: runtime::Options::new_for_load(self.num_threads, num_operations, self.seed),
+            preset: None,
         };
-        cmd.run()
+        cmd.run_dataset(dataset)
     }
 }
 
@@ -99,6 +158,7 @@ impl OpenCommand {
                     println!("  stat                 Show database statistics");
                     println!("  read <KEY>           Read the value for the given KEY");
                     println!("  write <KEY> <VALUE>  Write the VALUE for the given KEY");
+                    println!("  delete <KEY>         Delete the given KEY");
                 }
                 Some("stat") => match db.stat() {
                     Ok(stat) => println!("{stat}"),
@@ -109,8 +169,9 @@ impl OpenCommand {
                         println!("Usage: read <KEY>");
                         continue;
                     };
-                    match db.read(k.as_bytes()) {
-                        Ok(true) => println!("Some"),
+                    let mut v = Vec::new();
+                    match db.read(k.as_bytes(), &mut v) {
+                        Ok(true) => println!("Some({} bytes)", v.len()),
                         Ok(false) => println!("None"),
                         Err(e) => println!("Error: {e}"),
                     }
@@ -125,6 +186,16 @@ impl OpenCommand {
                         Err(e) => println!("Error: {e}"),
                     }
                 }
+                Some("delete") => {
+                    let Some(k) = args.next() else {
+                        println!("Usage: delete <KEY>");
+                        continue;
+                    };
+                    match db.delete(k.as_bytes()) {
+                        Ok(()) => println!("OK"),
+                        Err(e) => println!("Error: {e}"),
+                    }
+                }
                 Some(c) => println!("Unknown command '{c}', type 'help' to see available commands"),
                 _ => continue,
             }
@@ -132,17 +203,123 @@ impl OpenCommand {
     }
 }
 
+#[derive(Args, Debug)]
+struct VerifyCommand {
+    #[command(flatten)]
+    db: db::Options,
+    #[command(flatten)]
+    dataset: dataset::Options,
+}
+
+impl VerifyCommand {
+    /// The number of keys printed for each kind of error
+    const MAX_EXAMPLES: usize = 10;
+
+    fn run(self) -> Result<()> {
+        // Only these loads write every record
+        if !self.dataset.verify
+            && self.dataset.keys_file.is_none()
+            && self.dataset.kv_file.is_none()
+        {
+            bail!("verify requires --verify, or keys from a file");
+        }
+        let db = self.db.open()?;
+        let dataset = Dataset::new(self.dataset)?;
+        let mut expected = FnvHashSet::default();
+        for x in 0..dataset.num_records() {
+            let mut k = Vec::new();
+            dataset.encode(x, &mut k);
+            expected.insert(k);
+        }
+
+        let mut found = 0;
+        let mut corrupted = 0;
+        let mut unexpected = 0;
+        db.for_each(&mut |k, v| {
+            if !expected.remove(k) {
+                unexpected += 1;
+                if unexpected <= Self::MAX_EXAMPLES {
+                    println!("Unexpected: {}", k.escape_ascii());
+                }
+                return;
+            }
+            found += 1;
+            // Without --verify only the keys can be checked
+            if !dataset.verify() {
+                return;
+            }
+            if let Err(e) = dataset.check(k, v, None) {
+                corrupted += 1;
+                if corrupted <= Self::MAX_EXAMPLES {
+                    println!("Corrupted: {} ({e})", k.escape_ascii());
+                }
+            }
+        })?;
+        for k in expected.iter().take(Self::MAX_EXAMPLES) {
+            println!("Missing: {}", k.escape_ascii());
+        }
+
+        let missing = expected.len();
+        println!(
+            "Records: {} Found: {found} Missing: {missing} Corrupted: {corrupted} Unexpected: \
+             {unexpected}",
+            dataset.num_records()
+        );
+        if missing + corrupted + unexpected > 0 {
+            bail!("verification failed");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug, Serialize)]
+struct ReplayCommand {
+    #[command(flatten)]
+    db: db::Options,
+    #[command(flatten)]
+    output: report::Options,
+    #[arg(long)]
+    trace: PathBuf,
+    #[arg(long, value_enum, default_value_t = trace::Format::Dbbench)]
+    trace_format: trace::Format,
+    /// The number of threads to replay RocksDB traces with, dbbench traces
+    /// keep their threads
+    #[arg(long, short = 't', default_value_t = 1)]
+    num_threads: usize,
+    /// The number of keys read by each iterator seek of RocksDB traces
+    #[arg(long, default_value_t = 10)]
+    scan_length: usize,
+    /// Wait until the original time of each operation instead of running as
+    /// fast as possible
+    #[arg(long, default_value_t = false)]
+    preserve_timing: bool,
+}
+
+impl ReplayCommand {
+    fn run(self) -> Result<()> {
+        let threads = match self.trace_format {
+            trace::Format::Dbbench => trace::read(&self.trace),
+            trace::Format::Rocksdb => {
+                trace::read_rocksdb(&self.trace, self.num_threads, self.scan_length)
+            }
+        }
+        .with_context(|| format!("failed to read {}", self.trace.display()))?;
+        let reporter = Reporter::new(&self.output, &self)?;
+        let db = self.db.open()?;
+        runtime::replay(db, threads, reporter, self.preserve_timing)
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Command::Run(cmd) => {
-            println!("{cmd:#?}");
-            cmd.run()
-        }
-        Command::Load(cmd) => {
-            println!("{cmd:#?}");
+        Command::Run(mut cmd) => {
+            cmd.apply_preset();
             cmd.run()
         }
+        Command::Load(cmd) => cmd.run(),
         Command::Open(cmd) => cmd.run(),
+        Command::Verify(cmd) => cmd.run(),
+        Command::Replay(cmd) => cmd.run(),
     }
 }
@@ -0,0 +1,265 @@
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Write, stdout},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+#[derive(Clone, Debug, Args, Serialize)]
+#[group(skip)]
+pub struct Options {
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub output_format: Format,
+    /// Write results to this file instead of stdout
+    #[arg(long)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    /// One JSON object per line
+    Json,
+    /// One row per operation, the configuration is written as a leading `#`
+    /// comment line
+    Csv,
+}
+
+/// Latency statistics of one operation.
+#[derive(Clone, Debug, Serialize)]
+pub struct Stats {
+    pub operation: String,
+    pub unit: &'static str,
+    pub count: usize,
+    pub ops: f64,
+    pub bytes: u64,
+    /// Throughput in MiB/s
+    pub mbps: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub p9999: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Warmup,
+    Run,
+}
+
+impl Phase {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Warmup => "warmup",
+            Self::Run => "run",
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Warmup => "Warmup ",
+            Self::Run => "",
+        }
+    }
+}
+
+/// Statistics of one report interval or of the whole phase.
+#[derive(Clone, Debug, Serialize)]
+pub struct Record {
+    pub phase: Phase,
+    /// Seconds since the start of the phase
+    pub elapsed: f64,
+    pub total: usize,
+    pub failure: usize,
+    /// Reads of values that failed verification, only counted with --verify
+    pub corrupted: Option<usize>,
+    pub ops: f64,
+    pub operations: Vec<Stats>,
+}
+
+const CSV_HEADER: &str = "record,phase,elapsed,total,failure,corrupted,total_ops,operation,unit,\
+                          count,ops,bytes,mbps,p50,p90,p95,p99,p999,p9999,max,mean,stddev";
+
+pub struct Reporter {
+    format: Format,
+    config: Value,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Reporter {
+    /// Creates a reporter writing `config` ahead of the results, as a
+    /// debug dump in text format and a `#` comment line in CSV, while JSON
+    /// summaries embed it.
+    pub fn new<C: Serialize + Debug>(options: &Options, config: &C) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = match &options.output_file {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(stdout()),
+        };
+        let reporter = Self {
+            format: options.output_format,
+            config: serde_json::to_value(config)?,
+            writer: Mutex::new(writer),
+        };
+        {
+            let mut w = reporter.writer.lock().unwrap();
+            match reporter.format {
+                Format::Text => writeln!(w, "{config:#?}")?,
+                Format::Json => {}
+                Format::Csv => {
+                    writeln!(w, "# {}", reporter.config)?;
+                    writeln!(w, "{CSV_HEADER}")?;
+                }
+            }
+        }
+        Ok(reporter)
+    }
+
+    /// Writes a line of text format output, other formats carry the same
+    /// information in the configuration.
+    pub fn note(&self, line: &str) -> Result<()> {
+        if let Format::Text = self.format {
+            writeln!(self.writer.lock().unwrap(), "{line}")?;
+        }
+        Ok(())
+    }
+
+    pub fn interval(&self, record: &Record) -> Result<()> {
+        let mut w = self.writer.lock().unwrap();
+        match self.format {
+            Format::Text => {
+                writeln!(
+                    w,
+                    "--- {}Total: {} Failure: {}{} Elapsed: {}s ---",
+                    record.phase.prefix(),
+                    record.total,
+                    record.failure,
+                    corrupted_text(record),
+                    record.elapsed as u64
+                )?;
+                for s in &record.operations {
+                    writeln!(
+                        w,
+                        "{:15} - OPS: {:7}, P50: {:5}{unit}, P95: {:5}{unit}, P99: {:5}{unit}, \
+                         MAX: {:5}{unit}",
+                        s.operation,
+                        s.ops as u64,
+                        s.p50,
+                        s.p95,
+                        s.p99,
+                        s.max,
+                        unit = s.unit,
+                    )?;
+                }
+            }
+            Format::Json => {
+                let value = json!({"record": "interval", "interval": record});
+                writeln!(w, "{value}")?;
+            }
+            Format::Csv => write_csv(&mut *w, "interval", record)?,
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self, record: &Record) -> Result<()> {
+        let mut w = self.writer.lock().unwrap();
+        match self.format {
+            Format::Text => {
+                writeln!(w, "=== {}Summary ===", record.phase.prefix())?;
+                writeln!(
+                    w,
+                    "Total: {} Failure: {}{} Elapsed: {:.3}s OPS: {}",
+                    record.total,
+                    record.failure,
+                    corrupted_text(record),
+                    record.elapsed,
+                    record.ops as u64
+                )?;
+                for s in &record.operations {
+                    writeln!(
+                        w,
+                        "{:15} - Count: {}, OPS: {}, Bytes: {}, MB/s: {:.2}, P50: {}{unit}, \
+                         P90: {}{unit}, P99: {}{unit}, P99.9: {}{unit}, P99.99: {}{unit}, \
+                         MAX: {}{unit}, MEAN: {:.1}{unit}, STDDEV: {:.1}{unit}",
+                        s.operation,
+                        s.count,
+                        s.ops as u64,
+                        s.bytes,
+                        s.mbps,
+                        s.p50,
+                        s.p90,
+                        s.p99,
+                        s.p999,
+                        s.p9999,
+                        s.max,
+                        s.mean,
+                        s.stddev,
+                        unit = s.unit,
+                    )?;
+                }
+            }
+            Format::Json => {
+                let value = json!({
+                    "record": "summary",
+                    "config": self.config,
+                    "summary": record,
+                });
+                writeln!(w, "{value}")?;
+            }
+            Format::Csv => write_csv(&mut *w, "summary", record)?,
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+fn corrupted_text(record: &Record) -> String {
+    match record.corrupted {
+        Some(corrupted) => format!(" Corrupted: {corrupted}"),
+        None => String::new(),
+    }
+}
+
+fn write_csv(w: &mut impl Write, kind: &str, record: &Record) -> Result<()> {
+    let corrupted = record.corrupted.map(|n| n.to_string()).unwrap_or_default();
+    for s in &record.operations {
+        writeln!(
+            w,
+            "{kind},{},{:.3},{},{},{corrupted},{:.3},{},{},{},{:.3},{},{:.3},{},{},{},{},{},{},{},{:.3},\
+             {:.3}",
+            record.phase.as_str(),
+            record.elapsed,
+            record.total,
+            record.failure,
+            record.ops,
+            s.operation,
+            s.unit,
+            s.count,
+            s.ops,
+            s.bytes,
+            s.mbps,
+            s.p50,
+            s.p90,
+            s.p95,
+            s.p99,
+            s.p999,
+            s.p9999,
+            s.max,
+            s.mean,
+            s.stddev,
+        )?;
+    }
+    Ok(())
+}
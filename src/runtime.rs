@@ -1,113 +1,643 @@
 use std::{
+    path::PathBuf,
     sync::{
         Arc, Mutex,
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
     },
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+use clap::Args;
+use rand::{
+    Rng, SeedableRng, random,
+    rngs::{SmallRng, StdRng},
+};
+use serde::Serialize;
 
 use crate::{
-    dataset::Dataset,
-    db::Database,
+    dataset::{Access, Dataset},
+    db::{Database, modify},
+    report::{Phase, Record, Reporter, Stats},
+    trace::{Entry, TraceWriter},
     workload::{Operation, Workload},
 };
 
+#[derive(Clone, Debug, Args, Serialize)]
+#[group(skip)]
+pub struct Options {
+    #[arg(long, short = 't', default_value_t = 1)]
+    pub num_threads: usize,
+    /// Stop after this many operations [default: 1000000, unlimited with
+    /// --duration]
+    #[arg(long, short = 'o')]
+    pub num_operations: Option<usize>,
+    /// Stop after this long (e.g. 30s, 10m), whichever comes first with
+    /// --num-operations
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub duration: Option<Duration>,
+    /// Run this many operations before recording statistics
+    #[arg(long)]
+    pub warmup_ops: Option<usize>,
+    /// Run this long before recording statistics
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub warmup_duration: Option<Duration>,
+    /// Report the statistics of the warmup phase
+    #[arg(long, default_value_t = false)]
+    pub report_warmup: bool,
+    /// Issue operations on a fixed schedule at this rate across all threads
+    /// and also report response times measured from the intended start
+    #[arg(long)]
+    pub target_ops: Option<f64>,
+    /// Seed the per-thread random generators for reproducible runs
+    /// [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Log every operation after the warmup to this file for `replay`
+    #[arg(long)]
+    pub record_trace: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn new_for_load(num_threads: usize, num_operations: usize, seed: Option<u64>) -> Self {
+        Self {
+            num_threads,
+            num_operations: Some(num_operations),
+            duration: None,
+            warmup_ops: None,
+            warmup_duration: None,
+            report_warmup: false,
+            target_ops: None,
+            seed,
+            record_trace: None,
+        }
+    }
+}
+
 pub struct Runtime {
     db: Box<dyn Database>,
     dataset: Dataset,
     workload: Workload,
+    reporter: Arc<Reporter>,
 }
 
 impl Runtime {
-    pub fn new(db: Box<dyn Database>, dataset: Dataset, workload: Workload) -> Self {
+    pub fn new(
+        db: Box<dyn Database>,
+        dataset: Dataset,
+        workload: Workload,
+        reporter: Reporter,
+    ) -> Self {
         Self {
             db,
             dataset,
             workload,
+            reporter: Arc::new(reporter),
         }
     }
 
-    pub fn run(self, num_threads: usize, num_operations: usize) -> Result<()> {
-        let context = Arc::new(Context::new(
-            self.db,
-            self.dataset,
-            self.workload,
-            num_operations,
-        ));
-        let mut handles = Vec::with_capacity(num_threads);
-        for _ in 0..num_threads {
-            let context = context.clone();
-            handles.push(std::thread::spawn(move || context.run()));
+    pub fn run(self, options: Options) -> Result<()> {
+        if let Some(ops) = options.target_ops
+            && !(ops.is_finite() && ops > 0.0)
+        {
+            bail!("--target-ops must be a positive number, got {ops}");
+        }
+        if let Some(ops) = options.target_ops
+            && Duration::try_from_secs_f64(1.0 / ops).is_err()
+        {
+            bail!("--target-ops is too low, operations would never be due");
+        }
+        let trace = options
+            .record_trace
+            .as_deref()
+            .map(TraceWriter::create)
+            .transpose()?;
+        let context = Arc::new(Context {
+            db: self.db,
+            dataset: self.dataset,
+            workload: self.workload,
+            trace,
+        });
+
+        // Threads draw their seeds from this in spawn order
+        let seed = options.seed.unwrap_or_else(random);
+        let mut seeder = StdRng::seed_from_u64(seed);
+
+        if options.warmup_ops.is_some() || options.warmup_duration.is_some() {
+            let reporter = options.report_warmup.then(|| self.reporter.clone());
+            let statistics = Statistics::new(Phase::Warmup, reporter, context.dataset.verify());
+            let max_operations = options.warmup_ops.unwrap_or(usize::MAX);
+            let warmup = Limit::new(
+                statistics,
+                max_operations,
+                options.warmup_duration,
+                options.target_ops,
+            );
+            let warmup = context.run_limit(warmup, options.num_threads, &mut seeder)?;
+            if options.report_warmup {
+                warmup.statistics.finish()?;
+            }
         }
+
+        let max_operations = match (options.num_operations, options.duration) {
+            (Some(n), _) => n,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => 1_000_000,
+        };
+        let statistics = Statistics::new(Phase::Run, Some(self.reporter), context.dataset.verify());
+        let limit = Limit::new(
+            statistics,
+            max_operations,
+            options.duration,
+            options.target_ops,
+        );
+        let limit = context.run_limit(limit, options.num_threads, &mut seeder)?;
+        if let Some(trace) = &context.trace {
+            trace.finish()?;
+        }
+        limit.statistics.finish()
+    }
+}
+
+/// Re-issues the operations of a trace, each thread of the trace on a thread
+/// of its own.
+///
+/// If `preserve_timing`, operations wait until their original time since the
+/// start and response times are reported as well, otherwise they run as fast
+/// as possible.
+pub fn replay(
+    db: Box<dyn Database>,
+    threads: Vec<Vec<Entry>>,
+    reporter: Reporter,
+    preserve_timing: bool,
+) -> Result<()> {
+    let statistics = Statistics::new(Phase::Run, Some(Arc::new(reporter)), false);
+    let start = preserve_timing.then(Instant::now);
+    std::thread::scope(|s| {
+        let handles: Vec<_> = threads
+            .iter()
+            .map(|entries| s.spawn(|| replay_thread(&*db, entries, &statistics, start)))
+            .collect();
+        // Report the first error after all threads are done
+        let mut result = Ok(());
         for handle in handles {
-            handle.join().unwrap();
+            let r = handle.join().unwrap();
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        result
+    })?;
+    statistics.finish()
+}
+
+fn replay_thread(
+    db: &dyn Database,
+    entries: &[Entry],
+    statistics: &Statistics,
+    start: Option<Instant>,
+) -> Result<()> {
+    let mut rng = SmallRng::from_os_rng();
+    let mut v = Vec::new();
+    let mut old = Vec::new();
+    for entry in entries {
+        let intended = start.map(|start| start + entry.timestamp);
+        let now = Instant::now();
+        if let Some(intended) = intended
+            && intended > now
+        {
+            std::thread::sleep(intended - now);
+        }
+        let k = &entry.key;
+        let op = entry.op;
+        let timed = Timed {
+            db,
+            statistics,
+            op,
+            intended,
+        };
+        match op {
+            Operation::Read | Operation::MultiGet => {
+                timed.read(k, &mut v)?;
+            }
+            // Traces have no end keys, so range scans become scans of their
+            // length
+            Operation::Scan | Operation::RangeScan => timed.scan(k, entry.size, false)?,
+            Operation::ReverseScan => timed.scan(k, entry.size, true)?,
+            Operation::PrefixScan => timed.prefix_scan(k)?,
+            Operation::Write | Operation::Insert | Operation::WriteBatch => {
+                v.resize(entry.size, 0);
+                rng.fill(&mut v[..]);
+                timed.write(k, &v)?;
+            }
+            Operation::Delete => timed.delete(k)?,
+            Operation::ReadModifyWrite => {
+                v.resize(entry.size, 0);
+                rng.fill(&mut v[..]);
+                timed.read_modify_write(k, &v, &mut old, false)?;
+            }
         }
-        Ok(())
     }
+    Ok(())
 }
 
 struct Context {
     db: Box<dyn Database>,
     dataset: Dataset,
     workload: Workload,
+    trace: Option<TraceWriter>,
+}
+
+/// Bounds and paces the operations of a phase and collects its statistics.
+struct Limit {
     statistics: Statistics,
     max_operations: usize,
     num_operations: AtomicUsize,
+    start: Instant,
+    deadline: Option<Instant>,
+    // The rate of intended operation starts in open-loop mode
+    target_ops: Option<f64>,
 }
 
-impl Context {
+impl Limit {
     fn new(
-        db: Box<dyn Database>,
-        dataset: Dataset,
-        workload: Workload,
+        statistics: Statistics,
         max_operations: usize,
+        duration: Option<Duration>,
+        target_ops: Option<f64>,
     ) -> Self {
+        let start = Instant::now();
         Self {
-            db,
-            dataset,
-            workload,
-            statistics: Statistics::new(),
+            statistics,
             max_operations,
             num_operations: AtomicUsize::new(0),
+            start,
+            deadline: duration.map(|d| start + d),
+            target_ops,
+        }
+    }
+
+    /// Waits until the next operation is due, returns its intended start time
+    /// in open-loop mode, or `None` if the phase is over.
+    fn next(&self) -> Option<Option<Instant>> {
+        let current = self.num_operations.fetch_add(1, Ordering::Relaxed);
+        if current >= self.max_operations {
+            return None;
+        }
+        let intended = match self.target_ops {
+            // Operations due beyond any representable time never start
+            Some(ops) => Some(
+                Duration::try_from_secs_f64(current as f64 / ops)
+                    .ok()
+                    .and_then(|d| self.start.checked_add(d))?,
+            ),
+            None => None,
+        };
+        let now = Instant::now();
+        if let Some(intended) = intended
+            && intended > now
+        {
+            std::thread::sleep(intended - now);
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return None;
+        }
+        Some(intended)
+    }
+}
+
+impl Context {
+    fn run_limit(
+        self: &Arc<Self>,
+        limit: Limit,
+        num_threads: usize,
+        seeder: &mut StdRng,
+    ) -> Result<Arc<Limit>> {
+        let limit = Arc::new(limit);
+        let mut handles = Vec::with_capacity(num_threads);
+        for thread in 0..num_threads {
+            let context = self.clone();
+            let limit = limit.clone();
+            let rng = SmallRng::from_rng(seeder);
+            handles.push(std::thread::spawn(move || context.run(&limit, thread, rng)));
+        }
+        // Report the first error after all threads are done
+        let mut result = Ok(());
+        for handle in handles {
+            let r = handle.join().unwrap();
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        result.map(|()| limit)
+    }
+
+    /// Counts `v` as corrupted if it fails verification or is older than the
+    /// `expected` version, outside of the timed operation.
+    fn check(&self, statistics: &Statistics, k: &[u8], v: &[u8], expected: Option<u64>) {
+        if self.dataset.verify() && self.dataset.check(k, v, expected).is_err() {
+            statistics.record_corrupted();
         }
     }
 
-    fn run(&self) {
+    fn run(&self, limit: &Limit, thread: usize, mut rng: SmallRng) -> Result<()> {
+        let statistics = &limit.statistics;
+        // Only trace the run phase, with timestamps from its start
+        let mut trace = match statistics.phase {
+            Phase::Warmup => None,
+            Phase::Run => self.trace.as_ref().map(|t| t.buffer(thread, limit.start)),
+        };
         let mut k = Vec::new();
         let mut v = Vec::new();
-        while let Some(op) = self.next_operation() {
-            match op {
+        let mut old = Vec::new();
+        let mut end = Vec::new();
+        let mut batch: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        while let Some(intended) = limit.next() {
+            let time = intended.unwrap_or_else(Instant::now);
+            let op = self.workload.next(&mut rng);
+            let timed = Timed {
+                db: &*self.db,
+                statistics,
+                op,
+                intended,
+            };
+            // The value size or scan length to trace
+            let size = match op {
                 Operation::Read => {
-                    self.dataset.next(&mut k);
-                    self.statistics.record(op, || self.db.read(&k));
+                    let x = self.dataset.next(Access::Read, &mut k, &mut rng);
+                    let expected = self.dataset.expected_version(x);
+                    if timed.read(&k, &mut v)? {
+                        self.check(statistics, &k, &v, expected);
+                    }
+                    0
                 }
                 Operation::Scan => {
-                    self.dataset.next(&mut k);
-                    self.statistics.record(op, || self.db.scan(&k, 10));
+                    self.dataset.next(Access::Scan, &mut k, &mut rng);
+                    let n = self.workload.next_scan_length(&mut rng);
+                    timed.scan(&k, n, false)?;
+                    n
+                }
+                Operation::ReverseScan => {
+                    self.dataset.next(Access::Scan, &mut k, &mut rng);
+                    let n = self.workload.next_scan_length(&mut rng);
+                    timed.scan(&k, n, true)?;
+                    n
+                }
+                Operation::RangeScan => {
+                    let n = self.workload.next_scan_length(&mut rng);
+                    self.dataset
+                        .next_range(&mut k, &mut end, n as u64, &mut rng);
+                    timed.range_scan(&k, &end)?;
+                    n
+                }
+                Operation::PrefixScan => {
+                    self.dataset.next_prefix(&mut k, &mut rng);
+                    timed.prefix_scan(&k)?;
+                    0
                 }
                 Operation::Write => {
-                    self.dataset.next_record(&mut k, &mut v);
-                    self.statistics.record(op, || self.db.write(&k, &v));
+                    let write = self.dataset.next_record(&mut k, &mut v, &mut rng);
+                    let ok = timed.write(&k, &v)?;
+                    self.dataset.acknowledge_write(write, ok);
+                    v.len()
+                }
+                Operation::WriteBatch => {
+                    // Each write of the batch counts towards the limit, and
+                    // the batch is issued when its last write is due
+                    let n = self.workload.batch_size();
+                    batch.resize_with(n, Default::default);
+                    let mut writes = Vec::with_capacity(n);
+                    let mut len = 0;
+                    let mut last = intended;
+                    while len < n {
+                        if len > 0 {
+                            match limit.next() {
+                                Some(intended) => last = intended,
+                                None => break,
+                            }
+                        }
+                        let (k, v) = &mut batch[len];
+                        writes.push(self.dataset.next_record(k, v, &mut rng));
+                        len += 1;
+                    }
+                    let kvs: Vec<(&[u8], &[u8])> =
+                        batch[..len].iter().map(|(k, v)| (&k[..], &v[..])).collect();
+                    let result = statistics.record_items(op, last, len, || {
+                        self.db.write_batch(&kvs)?;
+                        Ok(kvs.iter().map(|(k, v)| k.len() + v.len()).sum())
+                    })?;
+                    for write in writes {
+                        self.dataset.acknowledge_write(write, result.is_some());
+                    }
+                    if let Some(duration) = result {
+                        statistics.record_per_item(PerItem::WriteBatchKey, duration, len);
+                    }
+                    // Trace the writes of the batch instead
+                    if let Some(trace) = &mut trace {
+                        for (k, v) in &kvs {
+                            trace.record(time, Operation::Write, k, v.len())?;
+                        }
+                    }
+                    continue;
+                }
+                Operation::MultiGet => {
+                    let n = self.workload.multi_get_size();
+                    keys.resize_with(n, Vec::new);
+                    let mut expected = Vec::with_capacity(n);
+                    for k in &mut keys {
+                        let x = self.dataset.next(Access::Read, k, &mut rng);
+                        expected.push(self.dataset.expected_version(x));
+                    }
+                    let keys: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+                    let mut values = Vec::new();
+                    let result = statistics.record(op, intended, || {
+                        values = self.db.multi_get(&keys)?;
+                        let mut bytes = 0;
+                        for (k, v) in keys.iter().zip(&values) {
+                            bytes += k.len() + v.as_ref().map_or(0, Vec::len);
+                        }
+                        Ok(bytes)
+                    })?;
+                    if let Some(duration) = result {
+                        statistics.record_per_item(PerItem::MultiGetKey, duration, n);
+                    }
+                    for ((k, v), expected) in keys.iter().zip(&values).zip(expected) {
+                        if let Some(v) = v {
+                            self.check(statistics, k, v, expected);
+                        }
+                    }
+                    // Trace the reads of the multi-get instead
+                    if let Some(trace) = &mut trace {
+                        for k in &keys {
+                            trace.record(time, Operation::Read, k, 0)?;
+                        }
+                    }
+                    continue;
                 }
+                Operation::Delete => {
+                    self.dataset.next(Access::Write, &mut k, &mut rng);
+                    timed.delete(&k)?;
+                    0
+                }
+                Operation::ReadModifyWrite => {
+                    let write = self.dataset.next_record(&mut k, &mut v, &mut rng);
+                    if self.workload.native_rmw() {
+                        let result = statistics.record(op, intended, || {
+                            self.db.read_modify_write(&k, &v)?;
+                            Ok(k.len() + v.len())
+                        })?;
+                        self.dataset.acknowledge_write(write, result.is_some());
+                    } else {
+                        // Verifiable values can only be replaced by a newer
+                        // version
+                        let expected = write.expected();
+                        let result =
+                            timed.read_modify_write(&k, &v, &mut old, self.dataset.verify())?;
+                        self.dataset.acknowledge_write(write, result.is_some());
+                        if result == Some(true) {
+                            self.check(statistics, &k, &old, expected);
+                        }
+                    }
+                    v.len()
+                }
+                Operation::Insert => {
+                    let x = self.dataset.next_insert(&mut k, &mut v, &mut rng);
+                    timed.write(&k, &v)?;
+                    // Failed inserts are acknowledged too, or the keyspace
+                    // would stop growing
+                    self.dataset.acknowledge_insert(x);
+                    v.len()
+                }
+            };
+            if let Some(trace) = &mut trace {
+                trace.record(time, op, &k, size)?;
             }
         }
+        if let Some(trace) = &mut trace {
+            trace.flush()?;
+        }
+        Ok(())
     }
+}
 
-    fn next_operation(&self) -> Option<Operation> {
-        let current = self.num_operations.fetch_add(1, Ordering::Relaxed);
-        if current >= self.max_operations {
-            None
-        } else {
-            Some(self.workload.next())
+/// One operation of a run or a replay, timed into its statistics.
+struct Timed<'a> {
+    db: &'a dyn Database,
+    statistics: &'a Statistics,
+    op: Operation,
+    intended: Option<Instant>,
+}
+
+impl Timed<'_> {
+    /// Reads `k` into `v`, returns whether it was found.
+    fn read(&self, k: &[u8], v: &mut Vec<u8>) -> Result<bool> {
+        let mut found = false;
+        self.statistics.record(self.op, self.intended, || {
+            found = self.db.read(k, v)?;
+            Ok(k.len() + if found { v.len() } else { 0 })
+        })?;
+        Ok(found)
+    }
+
+    /// Scans up to `n` rows from `k`, backwards if `reverse`, and records the
+    /// latency per row.
+    fn scan(&self, k: &[u8], n: usize, reverse: bool) -> Result<()> {
+        let mut rows = 0;
+        let result = self.statistics.record(self.op, self.intended, || {
+            let bytes;
+            (rows, bytes) = if reverse {
+                self.db.reverse_scan(k, n)?
+            } else {
+                self.db.scan(k, n)?
+            };
+            Ok(bytes)
+        })?;
+        if let Some(duration) = result {
+            let item = if reverse {
+                PerItem::ReverseScanRow
+            } else {
+                PerItem::ScanRow
+            };
+            self.statistics.record_per_item(item, duration, rows);
+        }
+        Ok(())
+    }
+
+    /// Scans the keys in `[start, end)` and records the latency per row.
+    fn range_scan(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        let mut rows = 0;
+        let result = self.statistics.record(self.op, self.intended, || {
+            let bytes;
+            (rows, bytes) = self.db.range_scan(start, end)?;
+            Ok(bytes)
+        })?;
+        if let Some(duration) = result {
+            self.statistics
+                .record_per_item(PerItem::RangeScanRow, duration, rows);
         }
+        Ok(())
+    }
+
+    fn prefix_scan(&self, k: &[u8]) -> Result<()> {
+        self.statistics
+            .record(self.op, self.intended, || self.db.prefix_scan(k))?;
+        Ok(())
+    }
+
+    /// Writes `v` to `k`, returns whether it succeeded.
+    fn write(&self, k: &[u8], v: &[u8]) -> Result<bool> {
+        let result = self.statistics.record(self.op, self.intended, || {
+            self.db.write(k, v)?;
+            Ok(k.len() + v.len())
+        })?;
+        Ok(result.is_some())
+    }
+
+    fn delete(&self, k: &[u8]) -> Result<()> {
+        self.statistics.record(self.op, self.intended, || {
+            self.db.delete(k)?;
+            Ok(k.len())
+        })?;
+        Ok(())
+    }
+
+    /// Reads `k` into `old` and writes it back modified with `v`, or writes
+    /// `v` itself if `replace` to keep `old` intact. Returns whether `old` was
+    /// found, or `None` if the operation failed.
+    fn read_modify_write(
+        &self,
+        k: &[u8],
+        v: &[u8],
+        old: &mut Vec<u8>,
+        replace: bool,
+    ) -> Result<Option<bool>> {
+        let mut found = false;
+        let result = self.statistics.record(self.op, self.intended, || {
+            let mut bytes = k.len() * 2;
+            found = self.db.read(k, old)?;
+            if found {
+                bytes += old.len();
+            } else {
+                old.clear();
+            }
+            if replace {
+                self.db.write(k, v)?;
+                return Ok(bytes + v.len());
+            }
+            modify(old, v);
+            self.db.write(k, old)?;
+            Ok(bytes + old.len())
+        })?;
+        Ok(result.map(|_| found))
     }
 }
 
 struct LastReport {
     time: Instant,
     histograms: [Histogram; Operation::COUNT],
+    response_histograms: [Histogram; Operation::COUNT],
+    per_item: [Histogram; PerItem::COUNT],
 }
 
 impl LastReport {
@@ -115,28 +645,65 @@ impl LastReport {
         Self {
             time: Instant::now(),
             histograms: Default::default(),
+            response_histograms: Default::default(),
+            per_item: Default::default(),
         }
     }
 }
 
+/// Latencies normalized to nanoseconds per item of multi-item operations.
+#[repr(usize)]
+#[derive(Copy, Clone, Debug)]
+enum PerItem {
+    ScanRow = 0,
+    WriteBatchKey = 1,
+    MultiGetKey = 2,
+    ReverseScanRow = 3,
+    RangeScanRow = 4,
+}
+
+impl PerItem {
+    const COUNT: usize = 5;
+    const NAMES: [&str; Self::COUNT] = [
+        "Scan/row",
+        "WriteBatch/key",
+        "MultiGet/key",
+        "ReverseScan/row",
+        "RangeScan/row",
+    ];
+}
+
 struct Statistics {
     start: Instant,
     count: AtomicUsize,
     failure: AtomicUsize,
+    // Values read that failed verification, if verifying
+    corrupted: Option<AtomicUsize>,
+    // Service times, from the actual start of operations
     histograms: [AtomicHistogram; Operation::COUNT],
+    // Response times, from the intended start of operations in open-loop mode
+    response_histograms: [AtomicHistogram; Operation::COUNT],
+    per_item: [AtomicHistogram; PerItem::COUNT],
     last_count: AtomicUsize,
     last_report: Mutex<LastReport>,
+    phase: Phase,
+    reporter: Option<Arc<Reporter>>,
 }
 
 impl Statistics {
-    fn new() -> Self {
+    fn new(phase: Phase, reporter: Option<Arc<Reporter>>, verify: bool) -> Self {
         Self {
             start: Instant::now(),
             count: AtomicUsize::new(0),
             failure: AtomicUsize::new(0),
+            corrupted: verify.then(|| AtomicUsize::new(0)),
             histograms: Default::default(),
+            response_histograms: Default::default(),
+            per_item: Default::default(),
             last_count: AtomicUsize::new(0),
             last_report: Mutex::new(LastReport::new()),
+            phase,
+            reporter,
         }
     }
 }
@@ -144,46 +711,98 @@ impl Statistics {
 impl Statistics {
     const REPORT_INTERVAL: Duration = Duration::from_secs(1);
 
-    fn record<F, O>(&self, op: Operation, f: F)
+    /// Records the latency of `f` and the bytes it returns, returns the
+    /// latency if `f` succeeded, or an error if the interval report could not
+    /// be written.
+    ///
+    /// If `intended` is given, the response time from it is recorded as well,
+    /// which accounts for the time the operation was queued behind slower
+    /// ones.
+    fn record<F>(&self, op: Operation, intended: Option<Instant>, f: F) -> Result<Option<Duration>>
     where
-        F: FnOnce() -> Result<O>,
+        F: FnOnce() -> Result<usize>,
+    {
+        self.record_items(op, intended, 1, f)
+    }
+
+    /// Like `record`, but the operation counts as `items` towards the total
+    /// and failures, such as the writes of a batch.
+    fn record_items<F>(
+        &self,
+        op: Operation,
+        intended: Option<Instant>,
+        items: usize,
+        f: F,
+    ) -> Result<Option<Duration>>
+    where
+        F: FnOnce() -> Result<usize>,
     {
         let start = Instant::now();
         let result = f();
-        let duration = start.elapsed();
-        self.count.fetch_add(1, Ordering::Relaxed);
-        match result {
-            Ok(_) => {
-                self.histograms[op as usize].add(duration);
+        let end = Instant::now();
+        let duration = end.duration_since(start);
+        self.count.fetch_add(items, Ordering::Relaxed);
+        let result = match result {
+            Ok(bytes) => {
+                self.histograms[op as usize].add(duration, bytes);
+                if let Some(intended) = intended {
+                    self.response_histograms[op as usize].add(end.duration_since(intended), bytes);
+                }
+                Some(duration)
             }
             Err(_) => {
-                self.failure.fetch_add(1, Ordering::Relaxed);
+                self.failure.fetch_add(items, Ordering::Relaxed);
+                None
             }
+        };
+        self.report()?;
+        Ok(result)
+    }
+
+    fn record_corrupted(&self) {
+        if let Some(corrupted) = &self.corrupted {
+            corrupted.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn record_per_item(&self, item: PerItem, duration: Duration, n: usize) {
+        if n > 0 {
+            let nanos = duration.as_nanos() / n as u128;
+            self.per_item[item as usize].increment(nanos as u64);
         }
-        self.report();
     }
 
-    fn report(&self) {
+    fn report(&self) -> Result<()> {
+        if self.reporter.is_none() {
+            return Ok(());
+        }
         let count = self.count.load(Ordering::Relaxed);
         let last_count = self.last_count.load(Ordering::Relaxed);
         if count - last_count < 1000 {
-            return;
+            return Ok(());
         }
         let Ok(mut last_report) = self.last_report.try_lock() else {
-            return;
+            return Ok(());
         };
 
         let now = Instant::now();
-        let interval = now.duration_since(last_report.time);
-        if interval < Self::REPORT_INTERVAL {
-            return;
+        if now.duration_since(last_report.time) < Self::REPORT_INTERVAL {
+            return Ok(());
         }
+        self.report_interval(&mut last_report, now, count)
+    }
+
+    fn report_interval(
+        &self,
+        last_report: &mut LastReport,
+        now: Instant,
+        count: usize,
+    ) -> Result<()> {
+        let interval = now.duration_since(last_report.time);
         last_report.time = now;
-        self.last_count.store(count, Ordering::Release);
+        let last_count = self.last_count.swap(count, Ordering::AcqRel);
 
-        let failure = self.failure.load(Ordering::Relaxed);
-        let elapsed = now.duration_since(self.start).as_secs();
-        println!("--- Total: {count} Failure: {failure} Elapsed: {elapsed}s ---");
+        let mut operations = Vec::new();
         for (i, (hist, last_hist)) in self
             .histograms
             .iter()
@@ -193,10 +812,95 @@ impl Statistics {
             let current_hist = hist.load();
             let interval_hist = current_hist.sub(last_hist);
             if interval_hist.count > 0 {
-                interval_hist.report(Operation::from(i), interval);
+                let name = format!("{:?}", Operation::from(i));
+                operations.push(interval_hist.stats(name, "us", interval));
+            }
+            *last_hist = current_hist;
+        }
+        for (i, (hist, last_hist)) in self
+            .response_histograms
+            .iter()
+            .zip(last_report.response_histograms.iter_mut())
+            .enumerate()
+        {
+            let current_hist = hist.load();
+            let interval_hist = current_hist.sub(last_hist);
+            if interval_hist.count > 0 {
+                let name = format!("{:?}/response", Operation::from(i));
+                operations.push(interval_hist.stats(name, "us", interval));
             }
             *last_hist = current_hist;
         }
+        for (i, (hist, last_hist)) in self
+            .per_item
+            .iter()
+            .zip(last_report.per_item.iter_mut())
+            .enumerate()
+        {
+            let current_hist = hist.load();
+            let interval_hist = current_hist.sub(last_hist);
+            if interval_hist.count > 0 {
+                let name = PerItem::NAMES[i].into();
+                operations.push(interval_hist.stats(name, "ns", interval));
+            }
+            *last_hist = current_hist;
+        }
+
+        let ops = (count - last_count) as f64 / interval.as_secs_f64();
+        match &self.reporter {
+            Some(reporter) => reporter.interval(&self.new_record(now, count, ops, operations)),
+            None => Ok(()),
+        }
+    }
+
+    /// Reports the last partial interval and a summary of the whole run.
+    fn finish(&self) -> Result<()> {
+        let now = Instant::now();
+        let count = self.count.load(Ordering::Relaxed);
+        let mut last_report = self.last_report.lock().unwrap();
+        if count > self.last_count.load(Ordering::Relaxed) {
+            self.report_interval(&mut last_report, now, count)?;
+        }
+
+        let elapsed = now.duration_since(self.start);
+        let mut operations = Vec::new();
+        for (i, hist) in self.histograms.iter().enumerate() {
+            let hist = hist.load();
+            if hist.count > 0 {
+                let name = format!("{:?}", Operation::from(i));
+                operations.push(hist.stats(name, "us", elapsed));
+            }
+        }
+        for (i, hist) in self.response_histograms.iter().enumerate() {
+            let hist = hist.load();
+            if hist.count > 0 {
+                let name = format!("{:?}/response", Operation::from(i));
+                operations.push(hist.stats(name, "us", elapsed));
+            }
+        }
+        for (i, hist) in self.per_item.iter().enumerate() {
+            let hist = hist.load();
+            if hist.count > 0 {
+                operations.push(hist.stats(PerItem::NAMES[i].into(), "ns", elapsed));
+            }
+        }
+        let ops = count as f64 / elapsed.as_secs_f64();
+        match &self.reporter {
+            Some(reporter) => reporter.summary(&self.new_record(now, count, ops, operations)),
+            None => Ok(()),
+        }
+    }
+
+    fn new_record(&self, now: Instant, count: usize, ops: f64, operations: Vec<Stats>) -> Record {
+        Record {
+            phase: self.phase,
+            elapsed: now.duration_since(self.start).as_secs_f64(),
+            total: count,
+            failure: self.failure.load(Ordering::Relaxed),
+            corrupted: self.corrupted.as_ref().map(|c| c.load(Ordering::Relaxed)),
+            ops,
+            operations,
+        }
     }
 }
 
@@ -206,6 +910,7 @@ const MAX_VALUE_POWER: u8 = 64;
 #[derive(Clone, Debug)]
 struct Histogram {
     count: usize,
+    bytes: u64,
     histogram: histogram::Histogram,
 }
 
@@ -213,31 +918,64 @@ impl Histogram {
     fn new() -> Self {
         Self {
             count: 0,
+            bytes: 0,
             histogram: histogram::Histogram::new(GROUPING_POWER, MAX_VALUE_POWER).unwrap(),
         }
     }
 
     fn sub(&self, other: &Self) -> Self {
         let count = self.count.checked_sub(other.count).unwrap();
+        let bytes = self.bytes.checked_sub(other.bytes).unwrap();
         let histogram = self.histogram.checked_sub(&other.histogram).unwrap();
-        Self { count, histogram }
-    }
-
-    fn report(&self, op: Operation, interval: Duration) {
-        let ops = self.count as f64 / interval.as_secs_f64();
-        let p50 = self.percentile(50.0);
-        let p95 = self.percentile(95.0);
-        let p99 = self.percentile(99.0);
-        let max = self.percentile(100.0);
-        println!(
-            "{:5} - OPS: {:7}, P50: {:5}us, P95: {:5}us, P99: {:5}us, MAX: {:5}us",
-            format!("{op:?}"),
-            ops as u64,
-            p50,
-            p95,
-            p99,
-            max
-        );
+        Self {
+            count,
+            bytes,
+            histogram,
+        }
+    }
+
+    fn stats(&self, operation: String, unit: &'static str, interval: Duration) -> Stats {
+        let (mean, stddev) = self.mean_stddev();
+        Stats {
+            operation,
+            unit,
+            count: self.count,
+            ops: self.count as f64 / interval.as_secs_f64(),
+            bytes: self.bytes,
+            mbps: self.bytes as f64 / interval.as_secs_f64() / (1024.0 * 1024.0),
+            p50: self.percentile(50.0),
+            p90: self.percentile(90.0),
+            p95: self.percentile(95.0),
+            p99: self.percentile(99.0),
+            p999: self.percentile(99.9),
+            p9999: self.percentile(99.99),
+            max: self.percentile(100.0),
+            mean,
+            stddev,
+        }
+    }
+
+    /// Estimates the mean and standard deviation from bucket midpoints.
+    fn mean_stddev(&self) -> (f64, f64) {
+        let mut n = 0.0;
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+        for bucket in &self.histogram {
+            if bucket.count() == 0 {
+                continue;
+            }
+            let count = bucket.count() as f64;
+            let value = (bucket.start() as f64 + bucket.end() as f64) / 2.0;
+            n += count;
+            sum += count * value;
+            sum_sq += count * value * value;
+        }
+        if n == 0.0 {
+            return (0.0, 0.0);
+        }
+        let mean = sum / n;
+        let variance = (sum_sq / n - mean * mean).max(0.0);
+        (mean, variance.sqrt())
     }
 
     fn percentile(&self, percentile: f64) -> u64 {
@@ -257,6 +995,7 @@ impl Default for Histogram {
 
 struct AtomicHistogram {
     count: AtomicUsize,
+    bytes: AtomicU64,
     histogram: histogram::AtomicHistogram,
 }
 
@@ -264,20 +1003,25 @@ impl AtomicHistogram {
     fn new() -> Self {
         Self {
             count: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
             histogram: histogram::AtomicHistogram::new(GROUPING_POWER, MAX_VALUE_POWER).unwrap(),
         }
     }
 
-    fn add(&self, duration: Duration) {
+    fn add(&self, duration: Duration, bytes: usize) {
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.increment(duration.as_micros() as u64);
+    }
+
+    fn increment(&self, value: u64) {
         self.count.fetch_add(1, Ordering::Relaxed);
-        self.histogram
-            .increment(duration.as_micros() as u64)
-            .unwrap();
+        self.histogram.increment(value).unwrap();
     }
 
     fn load(&self) -> Histogram {
         Histogram {
             count: self.count.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
             histogram: self.histogram.load(),
         }
     }
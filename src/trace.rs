@@ -0,0 +1,633 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::workload::Operation;
+
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum Format {
+    /// Written by `run --record-trace`
+    Dbbench,
+    /// Written by RocksDB `StartTrace`, column families share one keyspace
+    Rocksdb,
+}
+
+const MAGIC: &[u8; 8] = b"DBBTRACE";
+
+// Flush thread buffers to the file at this size
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// An operation of a trace.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Time since the start of the trace
+    pub timestamp: Duration,
+    pub op: Operation,
+    pub key: Vec<u8>,
+    /// The value size of writes or the length of scans
+    pub size: usize,
+}
+
+/// Writes a trace of `MAGIC` followed by entries of a little-endian u64
+/// timestamp in nanoseconds, u32 thread, u8 operation, u32 size, u32 key
+/// length and the key.
+pub struct TraceWriter {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Returns a buffer for the entries of one thread, which are written in
+    /// order with timestamps since `start`.
+    pub fn buffer(&self, thread: usize, start: Instant) -> TraceBuffer<'_> {
+        TraceBuffer {
+            trace: self,
+            start,
+            thread: thread as u32,
+            buf: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        self.writer.lock().unwrap().flush()?;
+        Ok(())
+    }
+}
+
+pub struct TraceBuffer<'a> {
+    trace: &'a TraceWriter,
+    start: Instant,
+    thread: u32,
+    buf: Vec<u8>,
+}
+
+impl TraceBuffer<'_> {
+    pub fn record(&mut self, time: Instant, op: Operation, k: &[u8], size: usize) -> Result<()> {
+        let timestamp = time.saturating_duration_since(self.start).as_nanos() as u64;
+        self.buf.extend_from_slice(&timestamp.to_le_bytes());
+        self.buf.extend_from_slice(&self.thread.to_le_bytes());
+        self.buf.push(op as u8);
+        self.buf.extend_from_slice(&(size as u32).to_le_bytes());
+        self.buf.extend_from_slice(&(k.len() as u32).to_le_bytes());
+        self.buf.extend_from_slice(k);
+        if self.buf.len() >= BUFFER_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.trace.writer.lock().unwrap().write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Reads a trace written by `TraceWriter`, returns the entries of each thread
+/// in order.
+pub fn read(path: &Path) -> Result<Vec<Vec<Entry>>> {
+    let data = fs::read(path)?;
+    let Some(mut data) = data.strip_prefix(MAGIC) else {
+        bail!("not a dbbench trace");
+    };
+    let mut threads: Vec<Vec<Entry>> = Vec::new();
+    while !data.is_empty() {
+        let Some((header, rest)) = data.split_first_chunk::<21>() else {
+            bail!("truncated entry");
+        };
+        let timestamp = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let thread = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let op = header[12] as usize;
+        let size = u32::from_le_bytes(header[13..17].try_into().unwrap()) as usize;
+        let klen = u32::from_le_bytes(header[17..].try_into().unwrap()) as usize;
+        if op >= Operation::COUNT {
+            bail!("invalid operation {op}");
+        }
+        if rest.len() < klen {
+            bail!("truncated key of {klen} bytes");
+        }
+        let (key, rest) = rest.split_at(klen);
+        data = rest;
+        if threads.len() <= thread {
+            threads.resize_with(thread + 1, Vec::new);
+        }
+        threads[thread].push(Entry {
+            timestamp: Duration::from_nanos(timestamp),
+            op: op.into(),
+            key: key.to_vec(),
+            size,
+        });
+    }
+    Ok(threads)
+}
+
+const ROCKSDB_MAGIC: &[u8] = b"feedcafedeadbeef";
+
+// RocksDB trace record types
+const ROCKSDB_BEGIN: u8 = 1;
+const ROCKSDB_END: u8 = 2;
+const ROCKSDB_WRITE: u8 = 3;
+const ROCKSDB_GET: u8 = 4;
+const ROCKSDB_ITERATOR_SEEK: u8 = 5;
+const ROCKSDB_ITERATOR_SEEK_FOR_PREV: u8 = 6;
+const ROCKSDB_MULTI_GET: u8 = 13;
+
+// The bits of the payload fields present in the payload map of trace version
+// 0.2, the fields follow in bit order
+const ROCKSDB_WRITE_BATCH: u32 = 1;
+const ROCKSDB_GET_CF: u32 = 2;
+const ROCKSDB_GET_KEY: u32 = 3;
+const ROCKSDB_ITERATOR_CF: u32 = 4;
+const ROCKSDB_ITERATOR_KEY: u32 = 5;
+const ROCKSDB_MULTI_GET_SIZE: u32 = 8;
+const ROCKSDB_MULTI_GET_CFS: u32 = 9;
+const ROCKSDB_MULTI_GET_KEYS: u32 = 10;
+
+/// Reads a RocksDB query trace, deals its entries round-robin to
+/// `num_threads` threads since it has no threads of its own.
+///
+/// Iterator seeks become forward or reverse scans of `scan_length` keys, write
+/// batches and multi-gets become an entry for each of their keys.
+pub fn read_rocksdb(
+    path: &Path,
+    num_threads: usize,
+    scan_length: usize,
+) -> Result<Vec<Vec<Entry>>> {
+    decode_rocksdb(&fs::read(path)?, num_threads, scan_length)
+}
+
+fn decode_rocksdb(
+    mut data: &[u8],
+    num_threads: usize,
+    scan_length: usize,
+) -> Result<Vec<Vec<Entry>>> {
+    let mut start = None;
+    // Trace versions before 0.2 have no payload maps
+    let mut legacy = false;
+    let mut entries = Vec::new();
+    while !data.is_empty() {
+        // Records are a u64 timestamp in microseconds, u8 type, u32 payload
+        // length and the payload
+        let Some((header, rest)) = data.split_first_chunk::<13>() else {
+            bail!("truncated record");
+        };
+        let ts = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let kind = header[8];
+        let len = u32::from_le_bytes(header[9..].try_into().unwrap()) as usize;
+        if rest.len() < len {
+            bail!("truncated payload of {len} bytes");
+        }
+        let (mut payload, rest) = rest.split_at(len);
+        data = rest;
+
+        let Some(start) = start else {
+            if kind != ROCKSDB_BEGIN || !payload.starts_with(ROCKSDB_MAGIC) {
+                bail!("not a RocksDB trace");
+            }
+            legacy = rocksdb_trace_version(payload)? < (0, 2);
+            start = Some(ts);
+            continue;
+        };
+        let timestamp = Duration::from_micros(ts.saturating_sub(start));
+        let mut push = |op, key: &[u8], size| {
+            entries.push(Entry {
+                timestamp,
+                op,
+                key: key.to_vec(),
+                size,
+            })
+        };
+        match kind {
+            ROCKSDB_END => break,
+            ROCKSDB_WRITE => {
+                let batch = if legacy {
+                    payload
+                } else {
+                    let map = read_u64(&mut payload)?;
+                    if map & (1 << ROCKSDB_WRITE_BATCH) == 0 {
+                        continue;
+                    }
+                    read_slice(&mut payload)?
+                };
+                read_write_batch(batch, &mut push)?;
+            }
+            ROCKSDB_GET => {
+                // Legacy payloads are the column family and the key
+                let key = if legacy {
+                    read_u32(&mut payload)?;
+                    read_slice(&mut payload)?
+                } else {
+                    let map = read_u64(&mut payload)?;
+                    if map & (1 << ROCKSDB_GET_CF) != 0 {
+                        read_u32(&mut payload)?;
+                    }
+                    if map & (1 << ROCKSDB_GET_KEY) == 0 {
+                        continue;
+                    }
+                    read_slice(&mut payload)?
+                };
+                push(Operation::Read, key, 0);
+            }
+            ROCKSDB_ITERATOR_SEEK | ROCKSDB_ITERATOR_SEEK_FOR_PREV => {
+                let key = if legacy {
+                    read_u32(&mut payload)?;
+                    read_slice(&mut payload)?
+                } else {
+                    // The bounds follow the key
+                    let map = read_u64(&mut payload)?;
+                    if map & (1 << ROCKSDB_ITERATOR_CF) != 0 {
+                        read_u32(&mut payload)?;
+                    }
+                    if map & (1 << ROCKSDB_ITERATOR_KEY) == 0 {
+                        continue;
+                    }
+                    read_slice(&mut payload)?
+                };
+                let op = match kind {
+                    ROCKSDB_ITERATOR_SEEK => Operation::Scan,
+                    _ => Operation::ReverseScan,
+                };
+                push(op, key, scan_length);
+            }
+            // Multi-gets are only traced since version 0.2
+            ROCKSDB_MULTI_GET => {
+                let map = read_u64(&mut payload)?;
+                if map & (1 << ROCKSDB_MULTI_GET_SIZE) != 0 {
+                    read_u32(&mut payload)?;
+                }
+                // The u32 column families and the length prefixed keys are
+                // each wrapped in a length prefixed slice
+                if map & (1 << ROCKSDB_MULTI_GET_CFS) != 0 {
+                    read_slice(&mut payload)?;
+                }
+                if map & (1 << ROCKSDB_MULTI_GET_KEYS) != 0 {
+                    let mut keys = read_slice(&mut payload)?;
+                    while !keys.is_empty() {
+                        push(Operation::Read, read_slice(&mut keys)?, 0);
+                    }
+                }
+            }
+            // Block cache and IO traces
+            _ => continue,
+        }
+    }
+
+    let mut threads = vec![Vec::new(); num_threads.max(1)];
+    let n = threads.len();
+    for (i, entry) in entries.into_iter().enumerate() {
+        threads[i % n].push(entry);
+    }
+    Ok(threads)
+}
+
+/// Parses `Trace Version: <MAJOR>.<MINOR>` from the header.
+fn rocksdb_trace_version(header: &[u8]) -> Result<(u32, u32)> {
+    let header = String::from_utf8_lossy(header);
+    let version = header
+        .split('\t')
+        .find_map(|field| field.strip_prefix("Trace Version: "))
+        .and_then(|v| v.trim().split_once('.'));
+    let Some((major, minor)) = version else {
+        bail!("no trace version in the header");
+    };
+    Ok((major.parse()?, minor.parse()?))
+}
+
+// Write batch record tags
+const BATCH_DELETION: u8 = 0x0;
+const BATCH_VALUE: u8 = 0x1;
+const BATCH_MERGE: u8 = 0x2;
+const BATCH_LOG_DATA: u8 = 0x3;
+const BATCH_CF_DELETION: u8 = 0x4;
+const BATCH_CF_VALUE: u8 = 0x5;
+const BATCH_CF_MERGE: u8 = 0x6;
+const BATCH_SINGLE_DELETION: u8 = 0x7;
+const BATCH_CF_SINGLE_DELETION: u8 = 0x8;
+const BATCH_BEGIN_PREPARE_XID: u8 = 0x9;
+const BATCH_END_PREPARE_XID: u8 = 0xA;
+const BATCH_COMMIT_XID: u8 = 0xB;
+const BATCH_ROLLBACK_XID: u8 = 0xC;
+const BATCH_NOOP: u8 = 0xD;
+const BATCH_CF_RANGE_DELETION: u8 = 0xE;
+const BATCH_RANGE_DELETION: u8 = 0xF;
+const BATCH_CF_BLOB_INDEX: u8 = 0x10;
+const BATCH_BLOB_INDEX: u8 = 0x11;
+const BATCH_BEGIN_PERSISTED_PREPARE_XID: u8 = 0x12;
+const BATCH_BEGIN_UNPREPARE_XID: u8 = 0x13;
+const BATCH_COMMIT_XID_AND_TIMESTAMP: u8 = 0x15;
+const BATCH_WIDE_COLUMN_ENTITY: u8 = 0x16;
+const BATCH_CF_WIDE_COLUMN_ENTITY: u8 = 0x17;
+const BATCH_VALUE_PREFERRED_SEQNO: u8 = 0x18;
+const BATCH_CF_VALUE_PREFERRED_SEQNO: u8 = 0x19;
+
+/// Calls `push` for the puts, merges and deletes of a write batch, range
+/// deletions are skipped.
+fn read_write_batch(
+    mut batch: &[u8],
+    push: &mut impl FnMut(Operation, &[u8], usize),
+) -> Result<()> {
+    // Sequence number and count
+    if batch.len() < 12 {
+        bail!("truncated write batch");
+    }
+    batch = &batch[12..];
+    while let Some((&tag, rest)) = batch.split_first() {
+        batch = rest;
+        let cf = matches!(
+            tag,
+            BATCH_CF_DELETION
+                | BATCH_CF_VALUE
+                | BATCH_CF_MERGE
+                | BATCH_CF_SINGLE_DELETION
+                | BATCH_CF_RANGE_DELETION
+                | BATCH_CF_BLOB_INDEX
+                | BATCH_CF_WIDE_COLUMN_ENTITY
+                | BATCH_CF_VALUE_PREFERRED_SEQNO
+        );
+        if cf {
+            read_varint32(&mut batch)?;
+        }
+        match tag {
+            BATCH_VALUE
+            | BATCH_CF_VALUE
+            | BATCH_BLOB_INDEX
+            | BATCH_CF_BLOB_INDEX
+            | BATCH_WIDE_COLUMN_ENTITY
+            | BATCH_CF_WIDE_COLUMN_ENTITY => {
+                let key = read_slice(&mut batch)?;
+                let value = read_slice(&mut batch)?;
+                push(Operation::Write, key, value.len());
+            }
+            // Timed put values end with a u64 write time
+            BATCH_VALUE_PREFERRED_SEQNO | BATCH_CF_VALUE_PREFERRED_SEQNO => {
+                let key = read_slice(&mut batch)?;
+                let value = read_slice(&mut batch)?;
+                push(Operation::Write, key, value.len().saturating_sub(8));
+            }
+            BATCH_MERGE | BATCH_CF_MERGE => {
+                let key = read_slice(&mut batch)?;
+                let value = read_slice(&mut batch)?;
+                push(Operation::ReadModifyWrite, key, value.len());
+            }
+            BATCH_DELETION
+            | BATCH_CF_DELETION
+            | BATCH_SINGLE_DELETION
+            | BATCH_CF_SINGLE_DELETION => {
+                push(Operation::Delete, read_slice(&mut batch)?, 0);
+            }
+            // The commit timestamp precedes the xid
+            BATCH_RANGE_DELETION | BATCH_CF_RANGE_DELETION | BATCH_COMMIT_XID_AND_TIMESTAMP => {
+                read_slice(&mut batch)?;
+                read_slice(&mut batch)?;
+            }
+            BATCH_LOG_DATA | BATCH_END_PREPARE_XID | BATCH_COMMIT_XID | BATCH_ROLLBACK_XID => {
+                read_slice(&mut batch)?;
+            }
+            BATCH_BEGIN_PREPARE_XID
+            | BATCH_BEGIN_PERSISTED_PREPARE_XID
+            | BATCH_BEGIN_UNPREPARE_XID
+            | BATCH_NOOP => {}
+            _ => bail!("unknown write batch tag {tag:#x}"),
+        }
+    }
+    Ok(())
+}
+
+fn read_u32(data: &mut &[u8]) -> Result<u32> {
+    let Some((b, rest)) = data.split_first_chunk::<4>() else {
+        bail!("truncated u32");
+    };
+    *data = rest;
+    Ok(u32::from_le_bytes(*b))
+}
+
+fn read_u64(data: &mut &[u8]) -> Result<u64> {
+    let Some((b, rest)) = data.split_first_chunk::<8>() else {
+        bail!("truncated u64");
+    };
+    *data = rest;
+    Ok(u64::from_le_bytes(*b))
+}
+
+fn read_varint32(data: &mut &[u8]) -> Result<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let Some((&b, rest)) = data.split_first() else {
+            bail!("truncated varint");
+        };
+        *data = rest;
+        value |= ((b & 0x7f) as u32) << shift;
+        if b & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("varint too long")
+}
+
+/// Reads a varint32 length prefixed slice.
+fn read_slice<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = read_varint32(data)? as usize;
+    if data.len() < len {
+        bail!("truncated slice of {len} bytes");
+    }
+    let (slice, rest) = data.split_at(len);
+    *data = rest;
+    Ok(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Summary = (u128, usize, Vec<u8>, usize);
+
+    fn summarize(threads: &[Vec<Entry>]) -> Vec<Vec<Summary>> {
+        threads
+            .iter()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|e| {
+                        (
+                            e.timestamp.as_micros(),
+                            e.op as usize,
+                            e.key.clone(),
+                            e.size,
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn rocksdb_record(ts: u64, kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut record = ts.to_le_bytes().to_vec();
+        record.push(kind);
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        record
+    }
+
+    fn rocksdb_header(version: &str) -> Vec<u8> {
+        let header = format!(
+            "feedcafedeadbeef\tTrace Version: {version}\tRocksDB Version: 10.4\tFormat: \
+             Timestamp OpType Payload\n"
+        );
+        rocksdb_record(1000, ROCKSDB_BEGIN, header.as_bytes())
+    }
+
+    /// Appends a length prefixed slice shorter than 128 bytes.
+    fn put_slice(buf: &mut Vec<u8>, slice: &[u8]) {
+        buf.push(slice.len() as u8);
+        buf.extend_from_slice(slice);
+    }
+
+    /// A batch of a put, a merge to column family 1 and a delete.
+    fn write_batch() -> Vec<u8> {
+        let mut batch = vec![0; 12];
+        batch.push(BATCH_VALUE);
+        put_slice(&mut batch, b"a");
+        put_slice(&mut batch, b"xyz");
+        batch.extend_from_slice(&[BATCH_CF_MERGE, 1]);
+        put_slice(&mut batch, b"b");
+        put_slice(&mut batch, b"12");
+        batch.push(BATCH_DELETION);
+        put_slice(&mut batch, b"c");
+        batch
+    }
+
+    fn expected_entries() -> Vec<Summary> {
+        vec![
+            (1, Operation::Write as usize, b"a".to_vec(), 3),
+            (1, Operation::ReadModifyWrite as usize, b"b".to_vec(), 2),
+            (1, Operation::Delete as usize, b"c".to_vec(), 0),
+            (2, Operation::Read as usize, b"g".to_vec(), 0),
+            (3, Operation::Scan as usize, b"s".to_vec(), 10),
+            (4, Operation::ReverseScan as usize, b"p".to_vec(), 10),
+        ]
+    }
+
+    #[test]
+    fn test_read_rocksdb_legacy() {
+        let mut trace = rocksdb_header("0.1");
+        trace.extend(rocksdb_record(1001, ROCKSDB_WRITE, &write_batch()));
+        for (ts, kind, key) in [
+            (1002, ROCKSDB_GET, b"g"),
+            (1003, ROCKSDB_ITERATOR_SEEK, b"s"),
+            (1004, ROCKSDB_ITERATOR_SEEK_FOR_PREV, b"p"),
+        ] {
+            let mut payload = 0u32.to_le_bytes().to_vec();
+            put_slice(&mut payload, key);
+            trace.extend(rocksdb_record(ts, kind, &payload));
+        }
+        trace.extend(rocksdb_record(1005, ROCKSDB_END, b""));
+        let threads = decode_rocksdb(&trace, 1, 10).unwrap();
+        assert_eq!(summarize(&threads), [expected_entries()]);
+    }
+
+    #[test]
+    fn test_read_rocksdb() {
+        // The payload map bits are spelled out as in RocksDB's TracePayloadType
+        let mut trace = rocksdb_header("0.2");
+        let mut payload = (1u64 << 1).to_le_bytes().to_vec();
+        put_slice(&mut payload, &write_batch());
+        trace.extend(rocksdb_record(1001, ROCKSDB_WRITE, &payload));
+
+        let map: u64 = 1 << 2 | 1 << 3;
+        let mut payload = map.to_le_bytes().to_vec();
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        put_slice(&mut payload, b"g");
+        trace.extend(rocksdb_record(1002, ROCKSDB_GET, &payload));
+
+        // With an upper bound after the key
+        let map: u64 = 1 << 4 | 1 << 5 | 1 << 7;
+        let mut payload = map.to_le_bytes().to_vec();
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        put_slice(&mut payload, b"s");
+        put_slice(&mut payload, b"t");
+        trace.extend(rocksdb_record(1003, ROCKSDB_ITERATOR_SEEK, &payload));
+
+        let mut payload = (1u64 << 5).to_le_bytes().to_vec();
+        put_slice(&mut payload, b"p");
+        trace.extend(rocksdb_record(
+            1004,
+            ROCKSDB_ITERATOR_SEEK_FOR_PREV,
+            &payload,
+        ));
+
+        let map: u64 = 1 << 8 | 1 << 9 | 1 << 10;
+        let mut payload = map.to_le_bytes().to_vec();
+        payload.extend_from_slice(&2u32.to_le_bytes());
+        put_slice(&mut payload, &[0; 8]);
+        let mut keys = Vec::new();
+        put_slice(&mut keys, b"m1");
+        put_slice(&mut keys, b"m2");
+        put_slice(&mut payload, &keys);
+        trace.extend(rocksdb_record(1005, ROCKSDB_MULTI_GET, &payload));
+        trace.extend(rocksdb_record(1006, ROCKSDB_END, b""));
+
+        let mut expected = expected_entries();
+        expected.push((5, Operation::Read as usize, b"m1".to_vec(), 0));
+        expected.push((5, Operation::Read as usize, b"m2".to_vec(), 0));
+        let threads = decode_rocksdb(&trace, 1, 10).unwrap();
+        assert_eq!(summarize(&threads), [expected.clone()]);
+
+        // Dealt round-robin
+        let threads = decode_rocksdb(&trace, 3, 10).unwrap();
+        let dealt: Vec<Vec<_>> = (0..3)
+            .map(|i| expected.iter().skip(i).step_by(3).cloned().collect())
+            .collect();
+        assert_eq!(summarize(&threads), dealt);
+    }
+
+    #[test]
+    fn test_trace_round_trip() {
+        let path = std::env::temp_dir().join(format!("dbbench-{}-trace", std::process::id()));
+        let trace = TraceWriter::create(&path).unwrap();
+        let start = Instant::now();
+        let mut buffers = [trace.buffer(0, start), trace.buffer(1, start)];
+        buffers[1]
+            .record(start + Duration::from_micros(5), Operation::Scan, b"b", 10)
+            .unwrap();
+        buffers[0]
+            .record(start, Operation::Write, b"a", 100)
+            .unwrap();
+        buffers[0]
+            .record(start + Duration::from_micros(7), Operation::Delete, b"", 0)
+            .unwrap();
+        for buffer in &mut buffers {
+            buffer.flush().unwrap();
+        }
+        trace.finish().unwrap();
+
+        let threads = read(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            summarize(&threads),
+            [
+                vec![
+                    (0, Operation::Write as usize, b"a".to_vec(), 100),
+                    (7, Operation::Delete as usize, Vec::new(), 0)
+                ],
+                vec![(5, Operation::Scan as usize, b"b".to_vec(), 10)],
+            ]
+        );
+    }
+}
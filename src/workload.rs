@@ -1,41 +1,210 @@
-use clap::Args;
-use rand::{Rng, distr::weighted::WeightedIndex, rng};
+use anyhow::{Context, Result, bail};
+use clap::{Args, ValueEnum};
+use rand::{Rng, distr::weighted::WeightedIndex};
+use serde::Serialize;
 
-#[derive(Clone, Debug, Args)]
+use crate::{
+    dataset::{self, Distribution},
+    generator::SizeGenerator,
+};
+
+#[derive(Clone, Debug, Args, Serialize)]
 #[group(skip)]
 pub struct Options {
     #[arg(long, short, default_value_t = 0.95)]
     pub read_ratio: f32,
     #[arg(long, short, default_value_t = 0.00)]
     pub scan_ratio: f32,
+    /// Scan backwards from the scan key
+    #[arg(long, default_value_t = 0.00)]
+    pub reverse_scan_ratio: f32,
+    /// Scan from the scan key up to the key of the scan length later records
+    #[arg(long, default_value_t = 0.00)]
+    pub range_scan_ratio: f32,
     #[arg(long, short, default_value_t = 0.05)]
     pub write_ratio: f32,
+    #[arg(long, default_value_t = 0.00)]
+    pub delete_ratio: f32,
+    #[arg(long, default_value_t = 0.00)]
+    pub read_modify_write_ratio: f32,
+    #[arg(long, default_value_t = 0.00)]
+    pub insert_ratio: f32,
+    /// Scan all keys under a sampled prefix, requires --num-prefixes
+    #[arg(long, default_value_t = 0.00)]
+    pub prefix_scan_ratio: f32,
+    #[arg(long, default_value_t = 0.00)]
+    pub multi_get_ratio: f32,
+    /// The number of keys read by each multi-get
+    #[arg(long, default_value_t = 10)]
+    pub multi_get_size: usize,
+    /// Use the database's native atomic read-modify-write instead of a
+    /// separate read and write
+    #[arg(long, default_value_t = false)]
+    pub native_rmw: bool,
+    /// Group this many writes into one atomic batch
+    #[arg(long, default_value_t = 1)]
+    pub batch_size: usize,
+    #[arg(long, default_value_t = 10)]
+    pub scan_length_min: usize,
+    /// The maximum scan length, ignored by the constant distribution
+    #[arg(long, default_value_t = 10)]
+    pub scan_length_max: usize,
+    #[arg(long, value_enum, default_value_t = ScanLengthDistribution::Constant)]
+    pub scan_length_distribution: ScanLengthDistribution,
 }
 
 impl Options {
-    pub fn new_for_load() -> Self {
+    pub fn new_for_load(batch_size: usize) -> Self {
         Self {
             read_ratio: 0.0,
             scan_ratio: 0.0,
+            reverse_scan_ratio: 0.0,
+            range_scan_ratio: 0.0,
             write_ratio: 1.0,
+            delete_ratio: 0.0,
+            read_modify_write_ratio: 0.0,
+            insert_ratio: 0.0,
+            prefix_scan_ratio: 0.0,
+            multi_get_ratio: 0.0,
+            multi_get_size: 10,
+            native_rmw: false,
+            batch_size,
+            scan_length_min: 10,
+            scan_length_max: 10,
+            scan_length_distribution: ScanLengthDistribution::Constant,
+        }
+    }
+}
+
+/// YCSB core workloads.
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize)]
+pub enum Preset {
+    /// Update heavy: 50% reads, 50% updates
+    A,
+    /// Read mostly: 95% reads, 5% updates
+    B,
+    /// Read only: 100% reads
+    C,
+    /// Read latest: 95% reads, 5% inserts
+    D,
+    /// Short ranges: 95% scans, 5% inserts
+    E,
+    /// Read-modify-write: 50% reads, 50% read-modify-writes
+    F,
+}
+
+impl Preset {
+    pub fn apply(self, workload: &mut Options, dataset: &mut dataset::Options) {
+        let (read, scan, write, insert, rmw) = match self {
+            Self::A => (0.5, 0.0, 0.5, 0.0, 0.0),
+            Self::B => (0.95, 0.0, 0.05, 0.0, 0.0),
+            Self::C => (1.0, 0.0, 0.0, 0.0, 0.0),
+            Self::D => (0.95, 0.0, 0.0, 0.05, 0.0),
+            Self::E => (0.0, 0.95, 0.0, 0.05, 0.0),
+            Self::F => (0.5, 0.0, 0.0, 0.0, 0.5),
+        };
+        workload.read_ratio = read;
+        workload.scan_ratio = scan;
+        workload.write_ratio = write;
+        workload.delete_ratio = 0.0;
+        workload.read_modify_write_ratio = rmw;
+        workload.insert_ratio = insert;
+        workload.prefix_scan_ratio = 0.0;
+        workload.multi_get_ratio = 0.0;
+        workload.reverse_scan_ratio = 0.0;
+        workload.range_scan_ratio = 0.0;
+        dataset.distribution = match self {
+            Self::D => Distribution::Latest,
+            _ => Distribution::Zipfian,
+        };
+        if let Self::E = self {
+            workload.scan_length_min = 1;
+            workload.scan_length_max = 100;
+            workload.scan_length_distribution = ScanLengthDistribution::Uniform;
         }
     }
 }
 
+#[derive(Clone, Debug, ValueEnum, Serialize)]
+pub enum ScanLengthDistribution {
+    /// Always scan the minimum length
+    Constant,
+    Uniform,
+    /// Favor shorter scans
+    Zipfian,
+}
+
 pub struct Workload {
     dist: WeightedIndex<f32>,
+    native_rmw: bool,
+    batch_size: usize,
+    multi_get_size: usize,
+    scan_length: SizeGenerator,
 }
 
 impl Workload {
-    pub fn new(options: Options) -> Self {
-        let dist =
-            WeightedIndex::new([options.read_ratio, options.scan_ratio, options.write_ratio])
-                .unwrap();
-        Self { dist }
+    pub fn new(options: Options) -> Result<Self> {
+        if options.batch_size == 0 {
+            bail!("--batch-size must be positive");
+        }
+        if options.multi_get_size == 0 {
+            bail!("--multi-get-size must be positive");
+        }
+        // Batch all writes
+        let (write_ratio, write_batch_ratio) = if options.batch_size > 1 {
+            (0.0, options.write_ratio)
+        } else {
+            (options.write_ratio, 0.0)
+        };
+        let min = options.scan_length_min;
+        let max = options.scan_length_max;
+        let range = "invalid --scan-length-min or --scan-length-max";
+        let scan_length = match options.scan_length_distribution {
+            ScanLengthDistribution::Constant => SizeGenerator::Constant(min),
+            ScanLengthDistribution::Uniform => SizeGenerator::uniform(min, max).context(range)?,
+            ScanLengthDistribution::Zipfian => SizeGenerator::zipfian(min, max).context(range)?,
+        };
+        let dist = WeightedIndex::new([
+            options.read_ratio,
+            options.scan_ratio,
+            write_ratio,
+            options.delete_ratio,
+            options.read_modify_write_ratio,
+            options.insert_ratio,
+            options.prefix_scan_ratio,
+            write_batch_ratio,
+            options.multi_get_ratio,
+            options.reverse_scan_ratio,
+            options.range_scan_ratio,
+        ])
+        .context("the --*-ratio operation ratios must be finite, non-negative and not all zero")?;
+        Ok(Self {
+            dist,
+            native_rmw: options.native_rmw,
+            batch_size: options.batch_size,
+            multi_get_size: options.multi_get_size,
+            scan_length,
+        })
+    }
+
+    pub fn next(&self, rng: &mut impl Rng) -> Operation {
+        rng.sample(&self.dist).into()
+    }
+
+    pub fn native_rmw(&self) -> bool {
+        self.native_rmw
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn multi_get_size(&self) -> usize {
+        self.multi_get_size
     }
 
-    pub fn next(&self) -> Operation {
-        rng().sample(&self.dist).into()
+    pub fn next_scan_length(&self, rng: &mut impl Rng) -> usize {
+        self.scan_length.next(rng)
     }
 }
 
@@ -45,10 +214,18 @@ pub enum Operation {
     Read = 0,
     Scan = 1,
     Write = 2,
+    Delete = 3,
+    ReadModifyWrite = 4,
+    Insert = 5,
+    PrefixScan = 6,
+    WriteBatch = 7,
+    MultiGet = 8,
+    ReverseScan = 9,
+    RangeScan = 10,
 }
 
 impl Operation {
-    pub const COUNT: usize = 3;
+    pub const COUNT: usize = 11;
 }
 
 impl From<usize> for Operation {
@@ -57,6 +234,14 @@ impl From<usize> for Operation {
             0 => Operation::Read,
             1 => Operation::Scan,
             2 => Operation::Write,
+            3 => Operation::Delete,
+            4 => Operation::ReadModifyWrite,
+            5 => Operation::Insert,
+            6 => Operation::PrefixScan,
+            7 => Operation::WriteBatch,
+            8 => Operation::MultiGet,
+            9 => Operation::ReverseScan,
+            10 => Operation::RangeScan,
             _ => unreachable!(),
         }
     }